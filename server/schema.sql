@@ -201,3 +201,125 @@ ALTER TABLE ONLY "player"
     ADD CONSTRAINT player_game_uuid_fkey FOREIGN KEY ("game_uuid") REFERENCES game(uuid) ON UPDATE CASCADE ON DELETE SET NULL;
 
 alter table player alter column name set not null;
+
+alter table game add column allow_spectators boolean default true not null;
+
+alter table player add column moderator boolean default false not null;
+
+alter table game add column invite_code text;
+alter table game alter column public set default true;
+
+CREATE UNIQUE INDEX game_invite_code_key ON game USING btree (invite_code);
+
+alter table game add column rated boolean default false not null;
+
+alter table player add column rating double precision default 1500 not null;
+
+create table game_result (
+    game_uuid text not null references game(uuid) on update cascade on delete cascade,
+    player_uuid text not null references player(uuid) on update cascade on delete cascade,
+    rank integer not null,
+    progress jsonb not null,
+    primary key (game_uuid, player_uuid)
+);
+
+create table rating_history (
+    id serial primary key,
+    player_uuid text not null references player(uuid) on update cascade on delete cascade,
+    game_uuid text not null references game(uuid) on update cascade on delete cascade,
+    rating_before double precision not null,
+    rating_after double precision not null,
+    created_at timestamp with time zone default now() not null
+);
+
+CREATE INDEX rating_history_player_uuid_idx ON rating_history USING btree (player_uuid);
+
+alter table game_result add column created_at timestamp with time zone default now() not null;
+
+create table game_move (
+    id serial primary key,
+    game_uuid text not null references game(uuid) on update cascade on delete cascade,
+    player_uuid text not null references player(uuid) on update cascade on delete cascade,
+    hex1_x integer not null,
+    hex1_y integer not null,
+    hex1_color integer not null,
+    hex2_x integer not null,
+    hex2_y integer not null,
+    hex2_color integer not null,
+    ingenious_count integer default 0 not null,
+    created_at timestamp with time zone default now() not null
+);
+
+CREATE INDEX game_move_game_uuid_idx ON game_move USING btree (game_uuid);
+CREATE INDEX game_move_player_uuid_idx ON game_move USING btree (player_uuid);
+
+alter table game add column seed bigint default 0 not null;
+alter table game_move add column move_number integer default 0 not null;
+
+create table game_seat (
+    game_uuid text not null references game(uuid) on update cascade on delete cascade,
+    player_uuid text not null references player(uuid) on update cascade on delete cascade,
+    seat integer not null,
+    primary key (game_uuid, seat)
+);
+
+create table game_draw (
+    id serial primary key,
+    game_uuid text not null references game(uuid) on update cascade on delete cascade,
+    player_uuid text not null references player(uuid) on update cascade on delete cascade,
+    move_number integer not null,
+    rack_index integer not null,
+    color1 integer not null,
+    color2 integer not null
+);
+
+CREATE INDEX game_draw_game_uuid_idx ON game_draw USING btree (game_uuid);
+
+alter table game add column move_limit_seconds integer;
+alter table game add column time_bank_seconds integer;
+alter table game add column increment_seconds integer default 0 not null;
+alter table game add column timeout_action text default 'auto_pass' not null;
+
+alter table game add column disconnected_seat text default 'skip' not null;
+
+alter table player add column bot text;
+
+create table puzzle (
+    id serial primary key,
+    seed bigint not null,
+    board_size integer not null,
+    moves integer not null,
+    target_points integer not null,
+    attempts integer default 0 not null,
+    solves integer default 0 not null,
+    created_at timestamp with time zone default now() not null,
+    unique (seed, board_size, moves)
+);
+
+create table puzzle_attempt (
+    id serial primary key,
+    puzzle_id integer not null references puzzle(id) on delete cascade,
+    player_uuid text not null references player(uuid) on update cascade on delete cascade,
+    points integer not null,
+    solved boolean not null,
+    created_at timestamp with time zone default now() not null
+);
+
+CREATE INDEX puzzle_attempt_puzzle_id_idx ON puzzle_attempt USING btree (puzzle_id);
+
+alter table game add column team_mode boolean default false not null;
+alter table game add column variant text default 'standard' not null;
+
+alter table game_move add column turn_number integer;
+
+create table game_pass (
+    id serial primary key,
+    game_uuid text not null references game(uuid) on update cascade on delete cascade,
+    player_uuid text not null references player(uuid) on update cascade on delete cascade,
+    turn_number integer not null
+);
+
+CREATE INDEX game_pass_game_uuid_idx ON game_pass USING btree (game_uuid);
+
+alter table puzzle add column board jsonb;
+alter table puzzle add column hex_pairs jsonb;
@@ -0,0 +1,153 @@
+// Plays bot-vs-bot games in process and prints statistics about them, to check the rules and the balance
+// without a server or a database.
+//
+//     cargo run --release --bin genial-sim -- --games 1000 --seed 1 --board-sizes 6,7,8 --bots easy,medium --format csv
+
+use std::collections::BTreeMap;
+use std::env;
+use std::process::exit;
+use std::thread;
+use serde::Serialize;
+use serde_json::json;
+use genial_server::game::COLORS;
+use genial_server::game::bot::BotDifficulty;
+use genial_server::game::rules::StandardRules;
+use genial_server::game::simulation::{simulate_game, SimulatedGame};
+use genial_server::types::Color;
+
+const USAGE: &str = "usage: genial-sim [--games N] [--seed N] [--board-sizes 6,7,8] [--bots easy,medium,hard] [--format json|csv]";
+
+struct Options {
+    games: usize,
+    seed: u64,
+    board_sizes: Vec<i32>,
+    bots: Vec<BotDifficulty>,
+    format: String,
+}
+
+fn parse_list<T>(value: &str, parse: impl Fn(&str) -> Option<T>) -> Option<Vec<T>> {
+    value.split(',').map(|item| parse(item.trim())).collect()
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        games: 100,
+        seed: rand::random(),
+        board_sizes: vec![6],
+        bots: vec![BotDifficulty::Easy, BotDifficulty::Easy],
+        format: "json".to_string(),
+    };
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or(format!("{} needs a value", flag))?;
+        let invalid = || format!("invalid value for {}: {}", flag, value);
+        match flag.as_str() {
+            "--games" => options.games = value.parse().map_err(|_| invalid())?,
+            "--seed" => options.seed = value.parse().map_err(|_| invalid())?,
+            "--board-sizes" => options.board_sizes = parse_list(value, |size| size.parse().ok().filter(|size| (6..=8).contains(size))).ok_or_else(invalid)?,
+            "--bots" => options.bots = parse_list(value, BotDifficulty::parse).filter(|bots| (2..=4).contains(&bots.len())).ok_or_else(invalid)?,
+            "--format" if value == "json" || value == "csv" => options.format = value.clone(),
+            _ => return Err(invalid()),
+        }
+    }
+
+    Ok(options)
+}
+
+#[derive(Serialize)]
+struct Statistics {
+    board_size: i32,
+    bots: Vec<&'static str>,
+    games: usize,
+    average_turns: f64,
+    average_placements: f64,
+    average_score_per_color: BTreeMap<Color, f64>, // over every seat
+    win_rate_per_seat: Vec<f64>, // shared wins count for every winner, the first seat moves first
+    first_player_advantage: f64, // first seat win rate minus the fair share
+    bonus_placements_per_game: f64,
+    bonus_placements_per_turn: f64,
+}
+
+fn summarize(board_size: i32, bots: &[BotDifficulty], games: &[SimulatedGame]) -> Statistics {
+    let game_count = games.len().max(1) as f64;
+    let seat_count = bots.len();
+    let turns: usize = games.iter().map(|game| game.turns).sum();
+    let bonus_placements: usize = games.iter().map(|game| game.bonus_placements).sum();
+
+    let average_score_per_color = COLORS.iter().map(|color| {
+        let total: f64 = games.iter().flat_map(|game| &game.progresses).map(|progress| progress.0[color] as f64).sum();
+        (*color, total / (game_count * seat_count as f64))
+    }).collect();
+
+    let win_rate_per_seat: Vec<f64> = (0..seat_count).map(|seat| {
+        games.iter().filter(|game| game.ranks[seat] == 1).count() as f64 / game_count
+    }).collect();
+
+    Statistics {
+        board_size,
+        bots: bots.iter().map(BotDifficulty::as_str).collect(),
+        games: games.len(),
+        average_turns: turns as f64 / game_count,
+        average_placements: games.iter().map(|game| game.placements).sum::<usize>() as f64 / game_count,
+        average_score_per_color,
+        first_player_advantage: win_rate_per_seat[0] - 1.0 / seat_count as f64,
+        win_rate_per_seat,
+        bonus_placements_per_game: bonus_placements as f64 / game_count,
+        bonus_placements_per_turn: bonus_placements as f64 / turns.max(1) as f64,
+    }
+}
+
+// game i of every board size uses seed + i, so a run can be repeated and the sizes compared on the same bags,
+// the games are spread over one thread per core
+fn simulate_games(options: &Options, board_size: i32) -> Vec<SimulatedGame> {
+    let thread_count = thread::available_parallelism().map_or(1, |count| count.get());
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..thread_count).map(|offset| scope.spawn(move || {
+            (offset..options.games).step_by(thread_count)
+                .map(|index| simulate_game(&StandardRules, options.seed.wrapping_add(index as u64), board_size, &options.bots))
+                .collect::<Vec<SimulatedGame>>()
+        })).collect();
+        handles.into_iter().flat_map(|handle| handle.join().expect("simulation thread panicked")).collect()
+    })
+}
+
+fn print_csv(statistics: &[Statistics]) {
+    println!("board_size,bots,metric,value");
+    statistics.iter().for_each(|stats| {
+        let mut rows: Vec<(String, f64)> = vec![
+            ("games".to_string(), stats.games as f64),
+            ("average_turns".to_string(), stats.average_turns),
+            ("average_placements".to_string(), stats.average_placements),
+            ("first_player_advantage".to_string(), stats.first_player_advantage),
+            ("bonus_placements_per_game".to_string(), stats.bonus_placements_per_game),
+            ("bonus_placements_per_turn".to_string(), stats.bonus_placements_per_turn),
+        ];
+        rows.extend(stats.average_score_per_color.iter().map(|(color, score)| (format!("average_score_color_{}", color), *score)));
+        rows.extend(stats.win_rate_per_seat.iter().enumerate().map(|(seat, rate)| (format!("win_rate_seat_{}", seat + 1), *rate)));
+
+        rows.iter().for_each(|(metric, value)| println!("{},{},{},{}", stats.board_size, stats.bots.join("+"), metric, value));
+    });
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_options(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            exit(2);
+        }
+    };
+
+    let statistics: Vec<Statistics> = options.board_sizes.iter().map(|board_size| {
+        let games = simulate_games(&options, *board_size);
+        summarize(*board_size, &options.bots, &games)
+    }).collect();
+
+    if options.format == "csv" {
+        print_csv(&statistics);
+    } else {
+        println!("{}", json!({ "seed": options.seed, "statistics": statistics }));
+    }
+}
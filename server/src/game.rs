@@ -1,10 +1,23 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::iter::{Iterator};
 use std::sync::{Arc, RwLock};
 use serde::Serialize;
-use crate::types::{BoardHex, BoardHexPair, Color, HexPair, Point, Progress};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::types::{BoardHex, BoardHexPair, Color, HexPair, HexPairs, Point, Progress};
 use lazy_static::lazy_static;
 
+pub mod notation;
+pub mod clock;
+pub mod bot;
+pub mod simulation;
+pub mod analysis;
+pub mod puzzle;
+pub mod solitaire;
+pub mod rules;
+pub mod undo;
+
 lazy_static! {
     pub static ref COLORS: Vec<Color> = {
         vec![0, 1, 2, 3, 4, 5]
@@ -134,10 +147,18 @@ pub fn equal_colors(board_hex_1: BoardHex, board_hex_2: BoardHex) -> bool {
 }
 
 pub fn is_valid_hex_pair_placement(board: &Vec<BoardHex>, board_size: i32, hex_pair: BoardHexPair) -> bool {
+    is_valid_hex_pair_placement_with(board, hex_pair, |point| is_coordinate_valid(point, board_size))
+}
+
+// the placement rules on a board whose shape is given by is_coordinate_valid
+pub fn is_valid_hex_pair_placement_with(board: &Vec<BoardHex>, hex_pair: BoardHexPair, is_coordinate_valid: impl Fn(&Point) -> bool) -> bool {
     let p1: Point = Point { x: hex_pair[0].x, y: hex_pair[0].y };
     let p2: Point = Point { x: hex_pair[1].x, y: hex_pair[1].y };
 
-    if !is_coordinate_valid(&p1, board_size) || !is_coordinate_valid(&p2, board_size) {
+    if !is_coordinate_valid(&p1) || !is_coordinate_valid(&p2) {
+        return false;
+    }
+    if !are_points_adjacent(&p1, &p2) {
         return false;
     }
 
@@ -158,11 +179,189 @@ pub fn is_valid_hex_pair_placement(board: &Vec<BoardHex>, board_size: i32, hex_p
     })
 }
 
+// the two hexes of a pair always cover neighboring points
+pub fn are_points_adjacent(point: &Point, other: &Point) -> bool {
+    DIRECTIONS.iter().any(|direction| get_next_point_in_direction(point.clone(), *direction) == *other)
+}
+
+// the placed colors have to be the ones of the rack's hex pair, in either orientation
+pub fn matches_rack_hex_pair(hex_pair: HexPair, board_hex_pair: BoardHexPair) -> bool {
+    let placed_colors = [board_hex_pair[0].color, board_hex_pair[1].color];
+    placed_colors == hex_pair || placed_colors == [hex_pair[1], hex_pair[0]]
+}
+
+// placements the player still has in this turn after the current one, None once the turn is over.
+// every color that reaches 18 with the placement adds one, colors that were at 18 before do not count again
+pub fn placements_left_in_turn(moves_in_turn: i8, ingenious_count: usize) -> Option<i8> {
+    (moves_in_turn.max(0) as usize + ingenious_count).checked_sub(1).map(|placements_left| placements_left as i8)
+}
+
+#[test]
+fn test_placement_rules() {
+    let board = vec![BoardHex { x: 0, y: 0, color: 1 }];
+    let adjacent = [BoardHex { x: 1, y: 0, color: 1 }, BoardHex { x: 2, y: 0, color: 2 }];
+    let apart = [BoardHex { x: 1, y: 0, color: 1 }, BoardHex { x: 3, y: 0, color: 2 }];
+    assert!(is_valid_hex_pair_placement(&board, 6, adjacent));
+    assert!(!is_valid_hex_pair_placement(&board, 6, apart));
+
+    assert!(matches_rack_hex_pair([1, 2], adjacent));
+    assert!(matches_rack_hex_pair([2, 1], adjacent));
+    assert!(!matches_rack_hex_pair([1, 1], adjacent));
+
+    assert_eq!(placements_left_in_turn(0, 0), None);
+    assert_eq!(placements_left_in_turn(0, 1), Some(0));
+    assert_eq!(placements_left_in_turn(0, 2), Some(1));
+    assert_eq!(placements_left_in_turn(1, 0), Some(0));
+    let mut progress = Progress::new();
+    progress.0.insert(1, 18);
+    let mut total_progress = progress.clone();
+    total_progress.0.insert(2, 18);
+    assert_eq!(ingenious_bonus_count(&progress, &total_progress), 1);
+}
+
+// the player with the higher lowest color wins, ties are broken by the next lowest color and so on
+pub fn compare_progress(progress: &Progress, other: &Progress) -> Ordering {
+    progress.sorted_values().cmp(&other.sorted_values())
+}
+
+// returns the rank of every player in the same order, 1 is the winner and tied players share a rank,
+// players that forfeited rank below everybody who did not
+pub fn rank_players(progresses: &[Progress], forfeited: &[bool]) -> Vec<usize> {
+    progresses.iter().zip(forfeited).map(|(progress, has_forfeited)| {
+        1 + progresses.iter().zip(forfeited)
+            .filter(|(other, other_forfeited)| {
+                (!**other_forfeited && *has_forfeited)
+                    || (*other_forfeited == has_forfeited && compare_progress(other, progress) == Ordering::Greater)
+            })
+            .count()
+    }).collect()
+}
+
+// every (rack index, placement) the rack allows on this board, both orientations of each hex pair included
+pub fn legal_placements(board: &Vec<BoardHex>, board_size: i32, hex_pairs: &HexPairs) -> Vec<(usize, BoardHexPair)> {
+    legal_placements_with(board, board_size, hex_pairs, |board_hex_pair| is_valid_hex_pair_placement(board, board_size, board_hex_pair))
+}
+
+// the candidates are every free point within board_size and its neighbors, is_valid_placement decides which are legal
+pub fn legal_placements_with(board: &Vec<BoardHex>, board_size: i32, hex_pairs: &HexPairs, is_valid_placement: impl Fn(BoardHexPair) -> bool) -> Vec<(usize, BoardHexPair)> {
+    let size = board_size as i8;
+    let points: Vec<Point> = (-size..=size)
+        .flat_map(|x| (-size..=size).map(move |y| Point { x, y }))
+        .filter(|point| !is_point_covered_with_hex(board, point))
+        .collect();
+
+    hex_pairs.iter().enumerate()
+        .filter_map(|(index, hex_pair)| hex_pair.map(|hex_pair| (index, hex_pair)))
+        .flat_map(|(index, hex_pair)| points.iter().flat_map(move |point| DIRECTIONS.iter().flat_map(move |direction| {
+            let neighbor = get_next_point_in_direction(point.clone(), *direction);
+            [
+                (index, [BoardHex { x: point.x, y: point.y, color: hex_pair[0] }, BoardHex { x: neighbor.x, y: neighbor.y, color: hex_pair[1] }]),
+                (index, [BoardHex { x: point.x, y: point.y, color: hex_pair[1] }, BoardHex { x: neighbor.x, y: neighbor.y, color: hex_pair[0] }]),
+            ]
+        })))
+        .filter(|(_, board_hex_pair)| is_valid_placement(*board_hex_pair))
+        .collect()
+}
+
+pub fn has_valid_placement(board: &Vec<BoardHex>, board_size: i32) -> bool {
+    has_valid_placement_with(board, board_size, |point| is_coordinate_valid(point, board_size))
+}
+
+pub fn has_valid_placement_with(board: &Vec<BoardHex>, board_size: i32, is_coordinate_valid: impl Fn(&Point) -> bool) -> bool {
+    let size = board_size as i8;
+    (-size..=size).any(|x| (-size..=size).any(|y| {
+        let point = Point { x, y };
+        is_coordinate_valid(&point) && !is_point_covered_with_hex(board, &point) && DIRECTIONS.iter().any(|direction| {
+            let neighbor = get_next_point_in_direction(point.clone(), *direction);
+            is_coordinate_valid(&neighbor) && !is_point_covered_with_hex(board, &neighbor)
+        })
+    }))
+}
+
+// the game ends when a player reaches 18 in every color, or when no more hex pairs can be placed
+pub fn is_game_over(progresses: &[Progress], racks_empty: bool, can_place: bool) -> bool {
+    progresses.iter().any(|progress| COLORS.iter().all(|color| progress.clone().is_genial(*color)))
+        || racks_empty
+        || !can_place
+}
+
+#[test]
+fn test_rank_players() {
+    let mut leader = Progress::new();
+    COLORS.iter().for_each(|color| { leader.0.insert(*color, 3); });
+    let mut runner_up = leader.clone();
+    runner_up.0.insert(0, 2);
+    runner_up.0.insert(1, 18);
+
+    assert_eq!(rank_players(&[runner_up.clone(), leader.clone(), leader.clone()], &[false, false, false]), vec![3, 1, 1]);
+    assert_eq!(rank_players(&[runner_up, leader.clone(), leader], &[false, false, true]), vec![2, 1, 3]);
+    assert!(has_valid_placement(&Vec::new(), 6));
+}
+
+// partners score together, every seat of a team gets the team's combined progress, seats without a team keep their own
+pub fn aggregate_team_progress(progresses: &[Progress], teams: &[Option<usize>]) -> Vec<Progress> {
+    progresses.iter().zip(teams).map(|(progress, team)| match team {
+        Some(team) => progresses.iter().zip(teams)
+            .filter(|(_, other_team)| **other_team == Some(*team))
+            .fold(Progress::new(), |acc, (other, _)| acc.sum(other.clone())),
+        None => progress.clone(),
+    }).collect()
+}
+
+#[test]
+fn test_aggregate_team_progress() {
+    let mut first = Progress::new();
+    first.0.insert(0, 10);
+    let mut second = Progress::new();
+    second.0.insert(0, 12);
+    second.0.insert(3, 2);
+    let progresses = [first.clone(), Progress::new(), second, Progress::new()];
+
+    let teams = aggregate_team_progress(&progresses, &[Some(0), Some(1), Some(0), Some(1)]);
+    assert_eq!(teams[0], teams[2]);
+    assert_eq!(teams[0].0[&0], 18);
+    assert_eq!(teams[2].0[&3], 2);
+    assert_eq!(aggregate_team_progress(&progresses, &[None; 4])[0], first);
+}
+
+// colors that reached 18 with this placement, each one is an Ingenious bonus placement
+pub fn ingenious_bonus_count(progress: &Progress, total_progress: &Progress) -> usize {
+    COLORS.iter().filter(|color| !progress.clone().is_genial(**color) && total_progress.clone().is_genial(**color)).count()
+}
+
+// replays placements given as (seat, hex pair) and returns the board and every seat's progress after each one
+pub fn replay_placements(placements: &[(usize, BoardHexPair)], seat_count: usize) -> Vec<(Vec<BoardHex>, Vec<Progress>)> {
+    let mut board: Vec<BoardHex> = Vec::new();
+    let mut progresses = vec![Progress::new(); seat_count];
+
+    placements.iter().filter(|(seat, _)| *seat < seat_count).map(|(seat, board_hex_pair)| {
+        let progress_gained = calculate_progress_gained(board.clone(), *board_hex_pair);
+        progresses[*seat] = progress_gained.sum(progresses[*seat].clone());
+        board.extend_from_slice(board_hex_pair);
+        (board.clone(), progresses.clone())
+    }).collect()
+}
+
+#[test]
+fn test_replay_placements() {
+    let first = [BoardHex { x: 0, y: 5, color: 4 }, BoardHex { x: 1, y: 5, color: 4 }];
+    let second = [BoardHex { x: -1, y: 5, color: 4 }, BoardHex { x: -2, y: 5, color: 1 }];
+
+    let steps = replay_placements(&[(0, first), (1, second)], 2);
+
+    assert_eq!(steps.len(), 2);
+    assert_eq!(steps[0].0.len(), 2);
+    assert_eq!(steps[1].0.len(), 4);
+    assert_eq!(steps[1].1[0], steps[0].1[0]);
+    assert!(steps[1].1[1].0[&4] > steps[0].1[1].0[&4]);
+}
+
 #[derive(Serialize, Clone)]
-pub struct HexPairsInBag(Arc<RwLock<Vec<HexPair>>>);
+pub struct HexPairsInBag(Arc<RwLock<Vec<HexPair>>>, #[serde(skip)] Arc<RwLock<StdRng>>);
 
 impl HexPairsInBag {
-    pub fn new() -> HexPairsInBag {
+    // bags with the same seed hand out the same hex pairs in the same order
+    pub fn new(seed: u64) -> HexPairsInBag {
         HexPairsInBag(Arc::new(RwLock::new(
             COLORS.iter().fold(Vec::new(), |mut acc, color| {
                 COLORS.iter().for_each(|inner_color| {
@@ -175,17 +374,46 @@ impl HexPairsInBag {
                 });
                 acc
             }).into_iter().collect()
-        )))
+        )), Arc::new(RwLock::new(StdRng::seed_from_u64(seed))))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.read().unwrap().is_empty()
     }
     pub fn take_random_hex_pair(self) -> Option<HexPair> {
         let mut writable = self.0.write().unwrap();
         if writable.is_empty() {
             return None;
         }
-        let index = (rand::random::<f32>() * writable.len() as f32).floor() as usize;
+        let index = self.1.write().unwrap().gen_range(0..writable.len());
         let value = writable.swap_remove(index);
         Some(value)
     }
+    // the random generator is part of the snapshot, after a restore the same hex pairs are drawn again
+    pub fn snapshot(&self) -> HexPairsInBagSnapshot {
+        HexPairsInBagSnapshot(self.0.read().unwrap().clone(), self.1.read().unwrap().clone())
+    }
+    pub fn restore(&self, snapshot: HexPairsInBagSnapshot) {
+        *self.0.write().unwrap() = snapshot.0;
+        *self.1.write().unwrap() = snapshot.1;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HexPairsInBagSnapshot(Vec<HexPair>, StdRng);
+
+// fills the empty rack slots from the bag and returns what was drawn as (rack index, hex pair)
+pub fn refill_rack(hex_pairs_in_bag: &HexPairsInBag, hex_pairs: &mut HexPairs) -> Vec<(usize, HexPair)> {
+    let mut draws = Vec::new();
+    for (index, slot) in hex_pairs.iter_mut().enumerate().filter(|(_, slot)| slot.is_none()) {
+        match hex_pairs_in_bag.clone().take_random_hex_pair() {
+            Some(hex_pair) => {
+                *slot = Some(hex_pair);
+                draws.push((index, hex_pair));
+            }
+            None => break,
+        }
+    }
+    draws
 }
 
 // #[test]
@@ -0,0 +1,94 @@
+// Ranks the placements of a rack for hints. Every legal placement is scored once and the best ones are listed
+// three ways: by points gained, by how the resulting score compares by the Ingenious rule (lowest color
+// first) and by bonus placements, immediate ones before colors that get close to 18.
+
+use std::cmp::Ordering;
+use serde::Serialize;
+use crate::game::rules::RuleSet;
+use crate::game::{compare_progress, COLORS};
+use crate::types::{BoardHex, BoardHexPair, HexPairs, Progress};
+
+const NEAR_INGENIOUS: u8 = 15;
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlacementAnalysis {
+    pub hex_pair_index: usize,
+    pub hex_pair: BoardHexPair,
+    pub points_gained: u32,
+    pub lowest_color_gained: u8, // how much the lowest score goes up
+    pub ingenious_bonuses: usize,
+    pub near_ingenious_colors: usize, // colors at 15 or more that are not at 18 yet
+    #[serde(skip)]
+    progress: Progress,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionAnalysis {
+    pub by_points: Vec<PlacementAnalysis>,
+    pub by_lowest_color: Vec<PlacementAnalysis>,
+    pub by_bonus: Vec<PlacementAnalysis>,
+}
+
+fn lowest(progress: &Progress) -> u8 {
+    progress.sorted_values().first().copied().unwrap_or(0)
+}
+
+fn analyze_placement(rules: &dyn RuleSet, board: &[BoardHex], progress: &Progress, hex_pair_index: usize, hex_pair: BoardHexPair) -> PlacementAnalysis {
+    let progress_gained = rules.progress_gained(board, hex_pair);
+    let total_progress = progress_gained.clone().sum(progress.clone());
+
+    PlacementAnalysis {
+        hex_pair_index,
+        hex_pair,
+        points_gained: progress_gained.0.values().map(|value| *value as u32).sum(),
+        lowest_color_gained: lowest(&total_progress) - lowest(progress),
+        ingenious_bonuses: rules.bonus_moves(progress, &total_progress),
+        near_ingenious_colors: COLORS.iter().filter(|color| (NEAR_INGENIOUS..18).contains(&total_progress.0[color])).count(),
+        progress: total_progress,
+    }
+}
+
+fn top(placements: &[PlacementAnalysis], count: usize, compare: impl Fn(&PlacementAnalysis, &PlacementAnalysis) -> Ordering) -> Vec<PlacementAnalysis> {
+    let mut ranked = placements.to_vec();
+    ranked.sort_by(|a, b| compare(b, a));
+    ranked.truncate(count);
+    ranked
+}
+
+pub fn analyze_position(rules: &dyn RuleSet, board: &Vec<BoardHex>, board_size: i32, progress: &Progress, hex_pairs: &HexPairs, count: usize) -> PositionAnalysis {
+    let placements: Vec<PlacementAnalysis> = rules.legal_placements(board, board_size, hex_pairs).into_iter()
+        .map(|(hex_pair_index, hex_pair)| analyze_placement(rules, board, progress, hex_pair_index, hex_pair))
+        .collect();
+
+    PositionAnalysis {
+        by_points: top(&placements, count, |a, b| a.points_gained.cmp(&b.points_gained).then(compare_progress(&a.progress, &b.progress))),
+        by_lowest_color: top(&placements, count, |a, b| compare_progress(&a.progress, &b.progress).then(a.points_gained.cmp(&b.points_gained))),
+        by_bonus: top(&placements, count, |a, b| {
+            a.ingenious_bonuses.cmp(&b.ingenious_bonuses)
+                .then(a.near_ingenious_colors.cmp(&b.near_ingenious_colors))
+                .then(a.points_gained.cmp(&b.points_gained))
+        }),
+    }
+}
+
+#[test]
+fn test_analyze_position() {
+    use crate::game::rules::StandardRules;
+
+    let board = vec![BoardHex { x: 0, y: 0, color: 1 }, BoardHex { x: 1, y: 0, color: 2 }];
+    let hex_pairs: HexPairs = [Some([1, 1]), None, Some([0, 0]), None, None, None];
+    let mut progress = Progress::new();
+    COLORS.iter().for_each(|color| { progress.0.insert(*color, 4); });
+    progress.0.insert(1, 17);
+
+    let analysis = analyze_position(&StandardRules, &board, 6, &progress, &hex_pairs, 3);
+
+    assert_eq!(analysis.by_points.len(), 3);
+    assert!(analysis.by_points.windows(2).all(|pair| pair[0].points_gained >= pair[1].points_gained));
+    // blue is one point away from 18, two blue hexes next to the blue hex earn the bonus
+    assert_eq!(analysis.by_bonus[0].ingenious_bonuses, 1);
+    assert_eq!(analysis.by_bonus[0].hex_pair_index, 0);
+    assert!(analyze_position(&StandardRules, &board, 6, &progress, &[None; 6], 3).by_points.is_empty());
+}
@@ -0,0 +1,146 @@
+// Computer players. Every bot picks one of the legal placements for its rack, they only differ in how the
+// placements are scored:
+//
+// - easy: greedy, the most points gained by this placement
+// - medium: balance, the best resulting score by the Ingenious rule, lowest color first
+// - hard: Monte-Carlo, the medium candidates are played out a few placements ahead with random hex pairs
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use crate::game::rules::RuleSet;
+use crate::game::COLORS;
+use crate::types::{BoardHex, BoardHexPair, HexPairs, Progress};
+
+const MONTE_CARLO_CANDIDATES: usize = 8;
+const MONTE_CARLO_PLAYOUTS: usize = 12;
+const MONTE_CARLO_DEPTH: usize = 3;
+const RANDOM_PLACEMENT_ATTEMPTS: usize = 50;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BotDifficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+
+impl BotDifficulty {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BotDifficulty::Easy => "easy",
+            BotDifficulty::Medium => "medium",
+            BotDifficulty::Hard => "hard",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<BotDifficulty> {
+        [BotDifficulty::Easy, BotDifficulty::Medium, BotDifficulty::Hard].into_iter().find(|difficulty| difficulty.as_str() == s)
+    }
+}
+
+fn points(progress: &Progress) -> u32 {
+    progress.0.values().map(|value| *value as u32).sum()
+}
+
+// the lowest color weighs the most, then the second lowest and so on, like comparing Ingenious scores
+fn balance_score(progress: &Progress) -> f64 {
+    progress.sorted_values().iter().rev().enumerate().map(|(index, value)| *value as f64 * 19f64.powi(index as i32)).sum()
+}
+
+fn progress_after(rules: &dyn RuleSet, board: &[BoardHex], progress: &Progress, board_hex_pair: BoardHexPair) -> Progress {
+    rules.progress_gained(board, board_hex_pair).sum(progress.clone())
+}
+
+// a bonus placement is worth about as much as another turn
+fn balance_value(rules: &dyn RuleSet, progress: &Progress, total_progress: &Progress) -> f64 {
+    balance_score(total_progress) + rules.bonus_moves(progress, total_progress) as f64 * 19f64.powi(5)
+}
+
+fn random_placement(rules: &dyn RuleSet, board: &Vec<BoardHex>, board_size: i32, rng: &mut impl Rng) -> Option<BoardHexPair> {
+    let size = board_size as i8;
+    (0..RANDOM_PLACEMENT_ATTEMPTS).find_map(|_| {
+        let (x, y) = (rng.gen_range(-size..=size), rng.gen_range(-size..=size));
+        let (dx, dy) = *[(-1, 0), (0, -1), (1, 0), (-1, 1), (0, 1), (1, -1)].choose(rng)?;
+        let colors = [*COLORS.choose(rng)?, *COLORS.choose(rng)?];
+        let board_hex_pair = [
+            BoardHex { x, y, color: colors[0] },
+            BoardHex { x: x + dx, y: y + dy, color: colors[1] },
+        ];
+        rules.is_valid_placement(board, board_size, board_hex_pair).then_some(board_hex_pair)
+    })
+}
+
+// plays the candidate and then a few random placements, the odd ones are the bot's own and the others stand in
+// for the opponents taking space on the board
+fn playout(rules: &dyn RuleSet, board: &[BoardHex], board_size: i32, progress: &Progress, candidate: BoardHexPair, rng: &mut impl Rng) -> f64 {
+    let mut board = board.to_vec();
+    let mut progress = progress_after(rules, &board, progress, candidate);
+    board.extend_from_slice(&candidate);
+
+    for depth in 0..MONTE_CARLO_DEPTH * 2 {
+        let Some(board_hex_pair) = random_placement(rules, &board, board_size, rng) else {
+            break;
+        };
+        if depth % 2 == 1 {
+            progress = progress_after(rules, &board, &progress, board_hex_pair);
+        }
+        board.extend_from_slice(&board_hex_pair);
+    }
+
+    balance_score(&progress)
+}
+
+pub fn choose_placement(rules: &dyn RuleSet, difficulty: BotDifficulty, board: &Vec<BoardHex>, board_size: i32, progress: &Progress, hex_pairs: &HexPairs, rng: &mut impl Rng) -> Option<(usize, BoardHexPair)> {
+    let mut placements = rules.legal_placements(board, board_size, hex_pairs);
+    // equal scores are broken randomly so bots do not always play the same corner
+    placements.shuffle(rng);
+
+    match difficulty {
+        BotDifficulty::Easy => placements.into_iter().max_by_key(|(_, board_hex_pair)| points(&rules.progress_gained(board, *board_hex_pair))),
+        BotDifficulty::Medium => placements.into_iter()
+            .map(|placement| (balance_value(rules, progress, &progress_after(rules, board, progress, placement.1)), placement))
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, placement)| placement),
+        BotDifficulty::Hard => {
+            let mut candidates: Vec<(f64, (usize, BoardHexPair))> = placements.into_iter()
+                .map(|placement| (balance_value(rules, progress, &progress_after(rules, board, progress, placement.1)), placement))
+                .collect();
+            candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+            candidates.truncate(MONTE_CARLO_CANDIDATES);
+
+            candidates.into_iter()
+                .map(|(value, placement)| {
+                    let playouts: f64 = (0..MONTE_CARLO_PLAYOUTS).map(|_| playout(rules, board, board_size, progress, placement.1, rng)).sum();
+                    // the immediate value still counts so bonus placements are not traded for luck
+                    (value + playouts / MONTE_CARLO_PLAYOUTS as f64, placement)
+                })
+                .max_by(|a, b| a.0.total_cmp(&b.0))
+                .map(|(_, placement)| placement)
+        }
+    }
+}
+
+#[test]
+fn test_bots_choose_legal_placements() {
+    use rand::SeedableRng;
+    use crate::game::rules::StandardRules;
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+    let board = vec![BoardHex { x: 0, y: 0, color: 1 }, BoardHex { x: 1, y: 0, color: 1 }, BoardHex { x: 2, y: 0, color: 1 }];
+    let hex_pairs: HexPairs = [Some([1, 1]), None, Some([3, 4]), None, None, None];
+    let mut progress = Progress::new();
+    progress.0.insert(3, 10);
+
+    for difficulty in [BotDifficulty::Easy, BotDifficulty::Medium, BotDifficulty::Hard] {
+        let (index, board_hex_pair) = choose_placement(&StandardRules, difficulty, &board, 6, &progress, &hex_pairs, &mut rng).unwrap();
+        assert!(hex_pairs[index].is_some());
+        assert!(StandardRules.is_valid_placement(&board, 6, board_hex_pair));
+    }
+
+    // extending the line of blue hexes scores more than any other placement
+    let (index, _) = choose_placement(&StandardRules, BotDifficulty::Easy, &board, 6, &progress, &hex_pairs, &mut rng).unwrap();
+    assert_eq!(index, 0);
+    assert!(choose_placement(&StandardRules, BotDifficulty::Medium, &board, 6, &progress, &[None; 6], &mut rng).is_none());
+}
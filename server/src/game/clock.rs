@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+const MIN_LIMIT_SECONDS: u32 = 5;
+const MAX_LIMIT_SECONDS: u32 = 7 * 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutAction {
+    #[default]
+    AutoPass,
+    RandomMove,
+    Forfeit,
+}
+
+impl TimeoutAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimeoutAction::AutoPass => "auto_pass",
+            TimeoutAction::RandomMove => "random_move",
+            TimeoutAction::Forfeit => "forfeit",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<TimeoutAction> {
+        [TimeoutAction::AutoPass, TimeoutAction::RandomMove, TimeoutAction::Forfeit].into_iter().find(|action| action.as_str() == s)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TimeControl {
+    pub moveLimitSeconds: Option<u32>, // time for a single turn, bonus placements included
+    pub timeBankSeconds: Option<u32>, // total time for the whole game
+    #[serde(default)]
+    pub incrementSeconds: u32, // added to the time bank after every turn
+    #[serde(default)]
+    pub timeoutAction: TimeoutAction,
+}
+
+impl TimeControl {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.moveLimitSeconds.is_none() && self.timeBankSeconds.is_none() {
+            return Err("time control needs a move limit or a time bank");
+        }
+        let in_range = |seconds: Option<u32>| seconds.is_none_or(|seconds| (MIN_LIMIT_SECONDS..=MAX_LIMIT_SECONDS).contains(&seconds));
+        if !in_range(self.moveLimitSeconds) || !in_range(self.timeBankSeconds) {
+            return Err("time limits must be between 5 seconds and 7 days");
+        }
+        if self.incrementSeconds > MAX_LIMIT_SECONDS {
+            return Err("increment is too long");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GameClock {
+    pub time_control: TimeControl,
+    time_banks: HashMap<Uuid, Duration>,
+    turn_started_at: Instant,
+}
+
+impl GameClock {
+    pub fn new(time_control: TimeControl, players: &[Uuid], now: Instant) -> GameClock {
+        let time_bank = Duration::from_secs(time_control.timeBankSeconds.unwrap_or(0) as u64);
+        GameClock {
+            time_control,
+            time_banks: players.iter().map(|player_uuid| (*player_uuid, time_bank)).collect(),
+            turn_started_at: now,
+        }
+    }
+
+    pub fn start_turn(&mut self, now: Instant) {
+        self.turn_started_at = now;
+    }
+
+    // charges the time spent to the player's bank and adds the increment
+    pub fn end_turn(&mut self, player_uuid: &Uuid, now: Instant) {
+        if self.time_control.timeBankSeconds.is_none() {
+            return;
+        }
+        let elapsed = now.saturating_duration_since(self.turn_started_at);
+        let increment = Duration::from_secs(self.time_control.incrementSeconds as u64);
+        if let Some(time_bank) = self.time_banks.get_mut(player_uuid) {
+            *time_bank = time_bank.saturating_sub(elapsed) + increment;
+        }
+    }
+
+    pub fn turn_deadline(&self, player_uuid: &Uuid) -> Option<Instant> {
+        let move_deadline = self.time_control.moveLimitSeconds.map(|seconds| self.turn_started_at + Duration::from_secs(seconds as u64));
+        let bank_deadline = self.time_control.timeBankSeconds.and(self.time_banks.get(player_uuid)).map(|time_bank| self.turn_started_at + *time_bank);
+        move_deadline.into_iter().chain(bank_deadline).min()
+    }
+
+    pub fn is_expired(&self, player_uuid: &Uuid, now: Instant) -> bool {
+        self.turn_deadline(player_uuid).is_some_and(|deadline| now >= deadline)
+    }
+
+    // what is left of the bank, for the player to move the running turn is already subtracted
+    pub fn time_bank(&self, player_uuid: &Uuid, player_to_move: Option<&Uuid>, now: Instant) -> Option<Duration> {
+        self.time_control.timeBankSeconds?;
+        let time_bank = *self.time_banks.get(player_uuid)?;
+        if player_to_move == Some(player_uuid) {
+            return Some(time_bank.saturating_sub(now.saturating_duration_since(self.turn_started_at)));
+        }
+        Some(time_bank)
+    }
+}
+
+#[test]
+fn test_game_clock_time_bank_and_increment() {
+    let players = [Uuid::new_v4(), Uuid::new_v4()];
+    let time_control = TimeControl { moveLimitSeconds: Some(30), timeBankSeconds: Some(60), incrementSeconds: 5, timeoutAction: TimeoutAction::AutoPass };
+    let start = Instant::now();
+    let mut clock = GameClock::new(time_control, &players, start);
+
+    assert_eq!(clock.turn_deadline(&players[0]), Some(start + Duration::from_secs(30)));
+    clock.end_turn(&players[0], start + Duration::from_secs(20));
+    assert_eq!(clock.time_bank(&players[0], None, start), Some(Duration::from_secs(45)));
+
+    clock.start_turn(start + Duration::from_secs(20));
+    clock.end_turn(&players[1], start + Duration::from_secs(45));
+    clock.start_turn(start + Duration::from_secs(45));
+    assert!(!clock.is_expired(&players[0], start + Duration::from_secs(74)));
+    assert!(clock.is_expired(&players[0], start + Duration::from_secs(75)));
+    assert!(TimeControl { moveLimitSeconds: None, timeBankSeconds: None, ..time_control }.validate().is_err());
+}
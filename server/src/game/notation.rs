@@ -0,0 +1,242 @@
+// Text notation for placements and whole games.
+//
+// A hex is its color letter followed by axial coordinates, a placement joins two hexes with a dash and ends
+// with one `!` for every color that reached 18, e.g. `R0,5-B1,5` or `G2,-3-G3,-3!`. A rack swap is `swap`.
+//
+// A game starts with PGN like header tags followed by numbered turns. The turn number is followed by the seat
+// that played the turn, `3.1` is the third turn played by seat 1, because seats that pass, time out or forfeit
+// break the seat order. A turn holds every placement made in it, a turn without one is `pass`:
+//
+//     [Name "friday game"]
+//     [BoardSize "6"]
+//     [Seed "42"]
+//     [Seat1 "aija"]
+//     [Seat2 "janis"]
+//     [Result "2-1"]
+//
+//     1.1 R0,5-B1,5 2.2 G2,-3-G3,-3! Y1,1-Y2,1 3.1 pass 4.2 swap R1,2-O2,2
+
+use std::fmt;
+use std::str::FromStr;
+use crate::types::{BoardHex, BoardHexPair, Color};
+
+const COLOR_LETTERS: [char; 6] = ['R', 'B', 'G', 'O', 'Y', 'V'];
+const SWAP: &str = "swap";
+const PASS: &str = "pass";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotationMove {
+    Placement { hex_pair: BoardHexPair, bonus: u8 },
+    Swap,
+}
+
+pub fn color_letter(color: Color) -> Option<char> {
+    COLOR_LETTERS.get(color as usize).copied()
+}
+
+pub fn parse_color_letter(letter: char) -> Option<Color> {
+    COLOR_LETTERS.iter().position(|color_letter| *color_letter == letter.to_ascii_uppercase()).map(|color| color as Color)
+}
+
+fn format_hex(hex: &BoardHex) -> String {
+    format!("{}{},{}", color_letter(hex.color).unwrap_or('?'), hex.x, hex.y)
+}
+
+fn parse_hex(text: &str) -> Result<BoardHex, String> {
+    let mut chars = text.chars();
+    let color = chars.next().and_then(parse_color_letter).ok_or(format!("unknown color in hex: {}", text))?;
+    let (x, y) = chars.as_str().split_once(',').ok_or(format!("missing coordinates in hex: {}", text))?;
+    let x = x.parse::<i8>().map_err(|_| format!("invalid x coordinate in hex: {}", text))?;
+    let y = y.parse::<i8>().map_err(|_| format!("invalid y coordinate in hex: {}", text))?;
+    Ok(BoardHex { x, y, color })
+}
+
+impl fmt::Display for NotationMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotationMove::Placement { hex_pair, bonus } => {
+                write!(f, "{}-{}{}", format_hex(&hex_pair[0]), format_hex(&hex_pair[1]), "!".repeat(*bonus as usize))
+            }
+            NotationMove::Swap => write!(f, "{}", SWAP),
+        }
+    }
+}
+
+impl FromStr for NotationMove {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case(SWAP) {
+            return Ok(NotationMove::Swap);
+        }
+
+        let placement = s.trim_end_matches('!');
+        let bonus = (s.len() - placement.len()) as u8;
+        // the second hex starts after the dash that follows a digit, coordinates themselves can be negative
+        let separator = placement.char_indices()
+            .skip(1)
+            .find(|(index, char)| *char == '-' && placement[..*index].ends_with(|previous: char| previous.is_ascii_digit()) && placement[index + 1..].starts_with(|next: char| next.is_ascii_alphabetic()))
+            .map(|(index, _)| index)
+            .ok_or(format!("invalid placement: {}", s))?;
+
+        Ok(NotationMove::Placement {
+            hex_pair: [parse_hex(&placement[..separator])?, parse_hex(&placement[separator + 1..])?],
+            bonus,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotationTurn {
+    pub seat: usize, // starts at 0, written starting at 1 like the seat tags
+    pub moves: Vec<NotationMove>, // empty when the seat passed
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GameRecord {
+    pub name: String,
+    pub board_size: i32,
+    pub seed: u64,
+    pub seats: Vec<String>, // player names in move order
+    pub ranks: Option<Vec<usize>>, // per seat, missing while the game is not finished
+    pub turns: Vec<NotationTurn>,
+}
+
+impl GameRecord {
+    // (seat, move) for every move in the order they were played
+    pub fn moves(&self) -> Vec<(usize, NotationMove)> {
+        self.turns.iter().flat_map(|turn| turn.moves.iter().map(move |notation_move| (turn.seat, *notation_move))).collect()
+    }
+}
+
+fn escape_tag_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[Name \"{}\"]", escape_tag_value(&self.name))?;
+        writeln!(f, "[BoardSize \"{}\"]", self.board_size)?;
+        writeln!(f, "[Seed \"{}\"]", self.seed)?;
+        for (seat, name) in self.seats.iter().enumerate() {
+            writeln!(f, "[Seat{} \"{}\"]", seat + 1, escape_tag_value(name))?;
+        }
+        if let Some(ranks) = &self.ranks {
+            writeln!(f, "[Result \"{}\"]", ranks.iter().map(|rank| rank.to_string()).collect::<Vec<String>>().join("-"))?;
+        }
+        writeln!(f)?;
+
+        let turns: Vec<String> = self.turns.iter().enumerate().map(|(turn_index, turn)| {
+            let moves: Vec<String> = turn.moves.iter().map(|notation_move| notation_move.to_string()).collect();
+            format!("{}.{} {}", turn_index + 1, turn.seat + 1, if moves.is_empty() { PASS.to_string() } else { moves.join(" ") })
+        }).collect();
+        writeln!(f, "{}", turns.join(" "))
+    }
+}
+
+fn parse_tag(line: &str) -> Result<(&str, String), String> {
+    let inner = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')).ok_or(format!("invalid tag: {}", line))?;
+    let (key, value) = inner.split_once(' ').ok_or(format!("invalid tag: {}", line))?;
+    let value = value.trim().strip_prefix('"').and_then(|value| value.strip_suffix('"')).ok_or(format!("tag value is not quoted: {}", line))?;
+    Ok((key, value.replace("\\\"", "\"").replace("\\\\", "\\")))
+}
+
+impl FromStr for GameRecord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut record = GameRecord::default();
+        let mut seats: Vec<(usize, String)> = Vec::new();
+        let mut move_text = String::new();
+
+        for line in s.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+            if !line.starts_with('[') {
+                move_text.push_str(line);
+                move_text.push(' ');
+                continue;
+            }
+
+            let (key, value) = parse_tag(line)?;
+            match key {
+                "Name" => record.name = value,
+                "BoardSize" => record.board_size = value.parse().map_err(|_| format!("invalid board size: {}", value))?,
+                "Seed" => record.seed = value.parse().map_err(|_| format!("invalid seed: {}", value))?,
+                "Result" => record.ranks = Some(value.split('-').map(|rank| rank.parse().map_err(|_| format!("invalid result: {}", value))).collect::<Result<_, _>>()?),
+                // unknown tags are skipped, like PGN readers do
+                _ => if let Some(seat) = key.strip_prefix("Seat").and_then(|seat| seat.parse::<usize>().ok()) {
+                    seats.push((seat, value));
+                },
+            }
+        }
+
+        seats.sort_by_key(|(seat, _)| *seat);
+        record.seats = seats.into_iter().map(|(_, name)| name).collect();
+        if record.seats.is_empty() {
+            return Err("game has no seats".to_string());
+        }
+        if record.ranks.as_ref().is_some_and(|ranks| ranks.len() != record.seats.len()) {
+            return Err("result does not have a rank for every seat".to_string());
+        }
+
+        // moves start with a letter, so a token of two numbers is always a turn
+        let mut passed = false;
+        for token in move_text.split_whitespace() {
+            let turn_and_seat = token.split_once('.').and_then(|(turn, seat)| Some((turn.parse::<usize>().ok()?, seat.parse::<usize>().ok()?)));
+            match turn_and_seat {
+                Some((turn, seat)) if turn == record.turns.len() + 1 => {
+                    check_turn_has_moves(&record, passed)?;
+                    if !(1..=record.seats.len()).contains(&seat) {
+                        return Err(format!("turn {} is played by seat {} but the game has {} seats", turn, seat, record.seats.len()));
+                    }
+                    record.turns.push(NotationTurn { seat: seat - 1, moves: Vec::new() });
+                    passed = false;
+                }
+                Some((turn, _)) => return Err(format!("expected turn {} but found turn {}", record.turns.len() + 1, turn)),
+                None => match record.turns.last_mut() {
+                    Some(_) if passed => return Err(format!("move after a pass: {}", token)),
+                    Some(turn) if token.eq_ignore_ascii_case(PASS) && turn.moves.is_empty() => passed = true,
+                    Some(turn) => turn.moves.push(token.parse()?),
+                    None => return Err(format!("move before the first turn number: {}", token)),
+                },
+            }
+        }
+        check_turn_has_moves(&record, passed)?;
+
+        Ok(record)
+    }
+}
+
+fn check_turn_has_moves(record: &GameRecord, passed: bool) -> Result<(), String> {
+    match record.turns.last() {
+        Some(turn) if turn.moves.is_empty() && !passed => Err(format!("turn {} has no moves, a turn without a placement is written as {}", record.turns.len(), PASS)),
+        _ => Ok(()),
+    }
+}
+
+#[test]
+fn test_notation_move_round_trip() {
+    let placement: NotationMove = "G2,-3-v3,-3!!".parse().unwrap();
+    assert_eq!(placement, NotationMove::Placement {
+        hex_pair: [BoardHex { x: 2, y: -3, color: 2 }, BoardHex { x: 3, y: -3, color: 5 }],
+        bonus: 2,
+    });
+    assert_eq!(placement.to_string(), "G2,-3-V3,-3!!");
+    assert_eq!("swap".parse::<NotationMove>(), Ok(NotationMove::Swap));
+    assert!("X0,0-R1,1".parse::<NotationMove>().is_err());
+}
+
+#[test]
+fn test_game_record_round_trip() {
+    let text = "[Name \"friday \\\"game\\\"\"]\n[BoardSize \"6\"]\n[Seed \"42\"]\n[Seat1 \"aija\"]\n[Seat2 \"janis\"]\n[Result \"2-1\"]\n\n1.1 R0,5-B1,5 2.2 G2,-3-G3,-3! Y1,1-Y2,1 3.1 pass 4.2 swap R1,2-O2,2 5.2 R2,2-R3,2\n";
+    let record: GameRecord = text.parse().unwrap();
+
+    assert_eq!(record.name, "friday \"game\"");
+    assert_eq!(record.ranks, Some(vec![2, 1]));
+    // seat 1 passed, so seat 2 plays two turns in a row
+    assert_eq!(record.moves().iter().map(|(seat, _)| *seat).collect::<Vec<usize>>(), vec![0, 1, 1, 1, 1, 1]);
+    assert_eq!(record.turns.len(), 5);
+    assert_eq!(record.to_string(), text);
+    assert!(text.replace("3.1 pass", "3.1").parse::<GameRecord>().is_err());
+    assert!(text.replace("3.1 pass", "3.3 pass").parse::<GameRecord>().is_err());
+}
@@ -0,0 +1,141 @@
+// Single-player puzzles: a board with some hex pairs already on it, a rack of six and a number of placements
+// to reach a target score with. Everything comes from the seed, the board, rack and target are stored with it
+// so a change of the rules or the generator can not change a puzzle that was handed out. The target is the best
+// score the solver finds, and the solver's own placements are checked like a player's before a puzzle is handed out.
+
+use std::cmp::Reverse;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use crate::game::matches_rack_hex_pair;
+use crate::game::rules::RuleSet;
+use crate::types::{BoardHex, BoardHexPair, HexPairs, Progress};
+
+pub const MIN_PUZZLE_MOVES: usize = 1;
+pub const MAX_PUZZLE_MOVES: usize = 3;
+const FILLED_PLACEMENTS: std::ops::RangeInclusive<usize> = 8..=16;
+const SOLVER_BEAM_WIDTH: usize = 6;
+const MIN_POINTS_PER_MOVE: u32 = 3; // easier puzzles are not worth solving
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Puzzle {
+    pub seed: u64,
+    pub board_size: i32,
+    pub board: Vec<BoardHex>,
+    pub hex_pairs: HexPairs,
+    pub moves: usize,
+    pub target_points: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct PuzzlePlacement {
+    pub hex_pair_index: usize,
+    pub hex_pair: BoardHexPair,
+}
+
+fn points(progress: &Progress) -> u32 {
+    progress.0.values().map(|value| *value as u32).sum()
+}
+
+// plays the placements from the puzzle's rack, every rack slot can be used once, and returns the points scored
+pub fn score_placements(rules: &dyn RuleSet, puzzle: &Puzzle, placements: &[PuzzlePlacement]) -> Result<u32, &'static str> {
+    if placements.len() > puzzle.moves {
+        return Err("too many placements");
+    }
+
+    let mut board = puzzle.board.clone();
+    let mut hex_pairs = puzzle.hex_pairs;
+    let mut progress = Progress::new();
+
+    for placement in placements {
+        let hex_pair = hex_pairs.get(placement.hex_pair_index).copied().flatten().ok_or("no hex pair in this rack slot")?;
+        if !matches_rack_hex_pair(hex_pair, placement.hex_pair) {
+            return Err("hex pair colors do not match the rack");
+        }
+        if !rules.is_valid_placement(&board, puzzle.board_size, placement.hex_pair) {
+            return Err("invalid hex pair placement");
+        }
+
+        progress = rules.progress_gained(&board, placement.hex_pair).sum(progress);
+        board.extend_from_slice(&placement.hex_pair);
+        hex_pairs[placement.hex_pair_index] = None;
+    }
+
+    Ok(points(&progress))
+}
+
+struct SolverState {
+    board: Vec<BoardHex>,
+    hex_pairs: HexPairs,
+    points: u32,
+    placements: Vec<PuzzlePlacement>,
+}
+
+// beam search, every level keeps the states with the most points so far
+pub fn solve(rules: &dyn RuleSet, board: &[BoardHex], board_size: i32, hex_pairs: &HexPairs, moves: usize) -> Option<(u32, Vec<PuzzlePlacement>)> {
+    let mut states = vec![SolverState { board: board.to_vec(), hex_pairs: *hex_pairs, points: 0, placements: Vec::new() }];
+
+    for _ in 0..moves {
+        let mut candidates: Vec<(u32, usize, usize, BoardHexPair)> = states.iter().enumerate()
+            .flat_map(|(state_index, state)| rules.legal_placements(&state.board, board_size, &state.hex_pairs).into_iter().map(move |(hex_pair_index, hex_pair)| {
+                let gained = points(&rules.progress_gained(&state.board, hex_pair));
+                (state.points + gained, state_index, hex_pair_index, hex_pair)
+            }))
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        candidates.sort_by_key(|candidate| Reverse(candidate.0));
+        candidates.truncate(SOLVER_BEAM_WIDTH);
+
+        states = candidates.into_iter().map(|(points, state_index, hex_pair_index, hex_pair)| {
+            let state = &states[state_index];
+            let mut next = SolverState { board: state.board.clone(), hex_pairs: state.hex_pairs, points, placements: state.placements.clone() };
+            next.board.extend_from_slice(&hex_pair);
+            next.hex_pairs[hex_pair_index] = None;
+            next.placements.push(PuzzlePlacement { hex_pair_index, hex_pair });
+            next
+        }).collect();
+    }
+
+    states.into_iter().next().map(|state| (state.points, state.placements))
+}
+
+// None when the seed makes a puzzle that is too easy or cannot be played, the caller tries another seed
+pub fn generate_puzzle(rules: &dyn RuleSet, seed: u64, board_size: i32, moves: usize) -> Option<Puzzle> {
+    let hex_pairs_in_bag = rules.hex_pairs_in_bag(seed);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut board: Vec<BoardHex> = Vec::new();
+
+    for _ in 0..rng.gen_range(FILLED_PLACEMENTS) {
+        let hex_pair = hex_pairs_in_bag.clone().take_random_hex_pair()?;
+        let (_, board_hex_pair) = *rules.legal_placements(&board, board_size, &[Some(hex_pair), None, None, None, None, None]).choose(&mut rng)?;
+        board.extend_from_slice(&board_hex_pair);
+    }
+
+    let mut hex_pairs: HexPairs = [None; 6];
+    rules.draw_hex_pairs(&hex_pairs_in_bag, &mut hex_pairs);
+
+    let (target_points, solution) = solve(rules, &board, board_size, &hex_pairs, moves)?;
+    let puzzle = Puzzle { seed, board_size, board, hex_pairs, moves, target_points };
+    let verified = score_placements(rules, &puzzle, &solution).is_ok_and(|points| points == target_points);
+
+    (verified && target_points >= MIN_POINTS_PER_MOVE * moves as u32).then_some(puzzle)
+}
+
+#[test]
+fn test_generate_and_score_puzzle() {
+    use crate::game::rules::StandardRules;
+
+    let puzzle = (0..20).find_map(|seed| generate_puzzle(&StandardRules, seed, 6, 2)).unwrap();
+    let same_puzzle = generate_puzzle(&StandardRules, puzzle.seed, 6, 2).unwrap();
+    assert_eq!(puzzle.board, same_puzzle.board);
+    assert_eq!(puzzle.target_points, same_puzzle.target_points);
+
+    let (points, solution) = solve(&StandardRules, &puzzle.board, 6, &puzzle.hex_pairs, 2).unwrap();
+    assert_eq!(score_placements(&StandardRules, &puzzle, &solution), Ok(points));
+    assert!(score_placements(&StandardRules, &puzzle, &[solution[0], solution[0]]).is_err());
+}
@@ -0,0 +1,113 @@
+// Every rule the server applies during a game goes through a RuleSet, the WebSocket handlers only ask it.
+// The defaults are the standard Ingenious rules, a variant or house rule overrides the parts it changes.
+
+use serde_json::json;
+use crate::game::solitaire::{is_solitaire_over, solitaire_score};
+use crate::game::{calculate_progress_gained, has_valid_placement_with, ingenious_bonus_count, is_coordinate_valid, is_game_over, is_valid_hex_pair_placement_with, legal_placements_with, rank_players, refill_rack, HexPairsInBag};
+use crate::types::{BoardHex, BoardHexPair, HexPair, HexPairs, Point, Progress};
+
+pub trait RuleSet: Sync {
+    fn hex_pairs_in_bag(&self, seed: u64) -> HexPairsInBag {
+        HexPairsInBag::new(seed)
+    }
+
+    // fills the empty rack slots, returns what was drawn as (rack index, hex pair)
+    fn draw_hex_pairs(&self, hex_pairs_in_bag: &HexPairsInBag, hex_pairs: &mut HexPairs) -> Vec<(usize, HexPair)> {
+        refill_rack(hex_pairs_in_bag, hex_pairs)
+    }
+
+    fn is_coordinate_valid(&self, point: &Point, board_size: i32) -> bool {
+        is_coordinate_valid(point, board_size)
+    }
+
+    fn is_valid_placement(&self, board: &Vec<BoardHex>, board_size: i32, board_hex_pair: BoardHexPair) -> bool {
+        is_valid_hex_pair_placement_with(board, board_hex_pair, |point| self.is_coordinate_valid(point, board_size))
+    }
+
+    fn legal_placements(&self, board: &Vec<BoardHex>, board_size: i32, hex_pairs: &HexPairs) -> Vec<(usize, BoardHexPair)> {
+        legal_placements_with(board, board_size, hex_pairs, |board_hex_pair| self.is_valid_placement(board, board_size, board_hex_pair))
+    }
+
+    fn has_valid_placement(&self, board: &Vec<BoardHex>, board_size: i32) -> bool {
+        has_valid_placement_with(board, board_size, |point| self.is_coordinate_valid(point, board_size))
+    }
+
+    fn progress_gained(&self, board: &[BoardHex], board_hex_pair: BoardHexPair) -> Progress {
+        calculate_progress_gained(board.to_vec(), board_hex_pair)
+    }
+
+    // extra placements in the same turn earned by going from one score to the other
+    fn bonus_moves(&self, progress_before: &Progress, progress_after: &Progress) -> usize {
+        ingenious_bonus_count(progress_before, progress_after)
+    }
+
+    fn is_game_over(&self, board: &Vec<BoardHex>, board_size: i32, progresses: &[Progress], racks_empty: bool) -> bool {
+        is_game_over(progresses, racks_empty, self.has_valid_placement(board, board_size))
+    }
+
+    // whatever the board, a game is over once a single side is left in it or everybody still in it passed in a row
+    fn is_game_decided(&self, side_count: usize, active_side_count: usize, passes_in_a_row: usize, active_player_count: usize) -> bool {
+        (side_count > 1 && active_side_count <= 1) || passes_in_a_row >= active_player_count
+    }
+
+    fn rank_players(&self, progresses: &[Progress], forfeited: &[bool]) -> Vec<usize> {
+        rank_players(progresses, forfeited)
+    }
+
+    // anything a variant scores on top of the ranking, sent with the game result
+    fn score_details(&self, _progress: &Progress) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+pub struct StandardRules;
+
+impl RuleSet for StandardRules {}
+
+pub struct SolitaireRules;
+
+impl RuleSet for SolitaireRules {
+    fn is_game_over(&self, board: &Vec<BoardHex>, board_size: i32, _progresses: &[Progress], racks_empty: bool) -> bool {
+        is_solitaire_over(racks_empty, self.has_valid_placement(board, board_size))
+    }
+
+    fn score_details(&self, progress: &Progress) -> Option<serde_json::Value> {
+        Some(json!(solitaire_score(progress)))
+    }
+}
+
+#[test]
+fn test_rule_sets_share_the_standard_placement_rules() {
+    let board = vec![BoardHex { x: 0, y: 0, color: 1 }];
+    let board_hex_pair = [BoardHex { x: 1, y: 0, color: 1 }, BoardHex { x: 2, y: 0, color: 2 }];
+    let mut progress = Progress::new();
+    progress.0.insert(1, 18);
+
+    for rules in [&StandardRules as &dyn RuleSet, &SolitaireRules] {
+        assert!(rules.is_valid_placement(&board, 6, board_hex_pair));
+        assert_eq!(rules.progress_gained(&board, board_hex_pair).0[&1], 1);
+        assert_eq!(rules.bonus_moves(&Progress::new(), &progress), 1);
+    }
+    assert!(StandardRules.score_details(&progress).is_none());
+    assert!(SolitaireRules.score_details(&progress).is_some());
+}
+
+#[test]
+fn test_placement_defaults_follow_the_board_shape_of_the_rule_set() {
+    struct LowerHalfRules;
+
+    impl RuleSet for LowerHalfRules {
+        fn is_coordinate_valid(&self, point: &Point, board_size: i32) -> bool {
+            point.y <= 0 && is_coordinate_valid(point, board_size)
+        }
+    }
+
+    let board = Vec::new();
+    let upper_half = [BoardHex { x: 0, y: 1, color: 1 }, BoardHex { x: 1, y: 1, color: 2 }];
+    assert!(StandardRules.is_valid_placement(&board, 6, upper_half));
+    assert!(!LowerHalfRules.is_valid_placement(&board, 6, upper_half));
+
+    let placements = LowerHalfRules.legal_placements(&board, 6, &[Some([1, 2]), None, None, None, None, None]);
+    assert!(!placements.is_empty());
+    assert!(placements.iter().all(|(_, board_hex_pair)| board_hex_pair.iter().all(|board_hex| board_hex.y <= 0)));
+}
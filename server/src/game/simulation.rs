@@ -0,0 +1,79 @@
+// Bot-vs-bot games played in memory with the same rules as the server, without clocks, the database or
+// connections. The seed decides the bag, the bots' tie breaks and so the whole game.
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use crate::game::bot::{choose_placement, BotDifficulty};
+use crate::game::rules::RuleSet;
+use crate::types::{BoardHex, HexPairs, Progress};
+
+pub struct SimulatedGame {
+    pub turns: usize,
+    pub placements: usize,
+    pub bonus_placements: usize,
+    pub progresses: Vec<Progress>, // in seat order, the first seat moves first
+    pub ranks: Vec<usize>,
+}
+
+fn racks_empty(racks: &[HexPairs]) -> bool {
+    racks.iter().all(|rack| rack.iter().all(Option::is_none))
+}
+
+// every seat is played by the bot of the same index
+pub fn simulate_game(rules: &dyn RuleSet, seed: u64, board_size: i32, bots: &[BotDifficulty]) -> SimulatedGame {
+    let seat_count = bots.len();
+    let hex_pairs_in_bag = rules.hex_pairs_in_bag(seed);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut board: Vec<BoardHex> = Vec::new();
+    let mut progresses = vec![Progress::new(); seat_count];
+    let mut racks: Vec<HexPairs> = vec![[None; 6]; seat_count];
+    racks.iter_mut().for_each(|rack| { rules.draw_hex_pairs(&hex_pairs_in_bag, rack); });
+
+    let (mut turns, mut placements, mut bonus_placements) = (0, 0, 0);
+    let mut passes_in_a_row = 0;
+    let mut seat = 0;
+
+    while !rules.is_game_decided(seat_count, seat_count, passes_in_a_row, seat_count) && !rules.is_game_over(&board, board_size, &progresses, racks_empty(&racks)) {
+        turns += 1;
+        let mut moves_left = 1;
+        let mut placed = false;
+
+        while moves_left > 0 {
+            let Some((hex_pair_index, board_hex_pair)) = choose_placement(rules, bots[seat], &board, board_size, &progresses[seat], &racks[seat], &mut rng) else {
+                break;
+            };
+            let total_progress = rules.progress_gained(&board, board_hex_pair).sum(progresses[seat].clone());
+            let ingenious_count = rules.bonus_moves(&progresses[seat], &total_progress);
+
+            progresses[seat] = total_progress;
+            racks[seat][hex_pair_index] = None;
+            board.extend_from_slice(&board_hex_pair);
+            placements += 1;
+            bonus_placements += ingenious_count;
+            moves_left += ingenious_count;
+            moves_left -= 1;
+            placed = true;
+        }
+
+        rules.draw_hex_pairs(&hex_pairs_in_bag, &mut racks[seat]);
+        passes_in_a_row = if placed { 0 } else { passes_in_a_row + 1 };
+        seat = (seat + 1) % seat_count;
+    }
+
+    let ranks = rules.rank_players(&progresses, &vec![false; seat_count]);
+    SimulatedGame { turns, placements, bonus_placements, progresses, ranks }
+}
+
+#[test]
+fn test_simulate_game_is_reproducible() {
+    use crate::game::rules::StandardRules;
+
+    let bots = [BotDifficulty::Easy, BotDifficulty::Easy];
+    let game = simulate_game(&StandardRules, 7, 6, &bots);
+    let same_game = simulate_game(&StandardRules, 7, 6, &bots);
+
+    assert!(game.placements > 0);
+    assert!(game.turns >= game.placements - game.bonus_placements);
+    assert_eq!(game.progresses, same_game.progresses);
+    assert!(game.ranks.contains(&1));
+}
@@ -0,0 +1,47 @@
+// Solitaire: one player works through the whole bag alone. Reaching 18 in a color does not end the game,
+// it goes on until the bag and the rack are empty or nothing fits on the board anymore. The result is scored
+// by thresholds per color instead of against other players.
+
+use serde::Serialize;
+use crate::game::COLORS;
+use crate::types::Progress;
+
+// every threshold a color reaches is worth a point, so a color scores 0 to 3 and the game 0 to 18
+pub const SOLITAIRE_THRESHOLDS: [u8; 3] = [6, 12, 18];
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SolitaireScore {
+    pub points: u32,
+    pub colors_at_threshold: [usize; 3], // how many colors reached each threshold
+}
+
+pub fn solitaire_score(progress: &Progress) -> SolitaireScore {
+    let colors_at_threshold = SOLITAIRE_THRESHOLDS.map(|threshold| COLORS.iter().filter(|color| progress.0[color] >= threshold).count());
+    SolitaireScore {
+        points: colors_at_threshold.iter().sum::<usize>() as u32,
+        colors_at_threshold,
+    }
+}
+
+pub fn is_solitaire_over(racks_empty: bool, can_place: bool) -> bool {
+    racks_empty || !can_place
+}
+
+#[test]
+fn test_solitaire_score() {
+    let mut progress = Progress::new();
+    progress.0.insert(0, 18);
+    progress.0.insert(1, 12);
+    progress.0.insert(2, 7);
+    progress.0.insert(3, 5);
+
+    let score = solitaire_score(&progress);
+    assert_eq!(score.colors_at_threshold, [3, 2, 1]);
+    assert_eq!(score.points, 6);
+
+    // reaching 18 everywhere does not end a solitaire game, an empty bag and rack does
+    use crate::game::rules::{RuleSet, SolitaireRules};
+    COLORS.iter().for_each(|color| { progress.0.insert(*color, 18); });
+    assert!(!SolitaireRules.is_game_over(&Vec::new(), 6, &[progress.clone()], false));
+    assert!(SolitaireRules.is_game_over(&Vec::new(), 6, &[progress], true));
+}
@@ -0,0 +1,93 @@
+// Placements are made reversible by a snapshot of everything they change, taken just before the placement.
+// Restoring it takes the hex pair off the board, puts it back in the rack and returns the drawn hex pairs to
+// the bag. The bag's random generator is restored with it, so undoing a placement can not change the draws.
+
+use uuid::Uuid;
+use crate::game::clock::GameClock;
+use crate::game::HexPairsInBagSnapshot;
+use crate::types::{BoardHex, BoardHexPair, Game, HexPairs, Player, Progress};
+
+#[derive(Debug, Clone)]
+pub struct PlacementSnapshot {
+    pub player_uuid: Uuid,
+    pub move_number: u32, // the placement was persisted with this number
+    pub board_hex_pair: BoardHexPair,
+    hex_pairs: HexPairs,
+    progress: Progress,
+    moves_in_turn: i8,
+    bag: HexPairsInBagSnapshot,
+    player_to_move: Option<Uuid>,
+    turn_count: u32,
+    passes_in_a_row: usize,
+    clock: Option<GameClock>,
+}
+
+impl PlacementSnapshot {
+    pub fn new(game: &Game, player: &Player, board_hex_pair: BoardHexPair) -> PlacementSnapshot {
+        PlacementSnapshot {
+            player_uuid: player.uuid,
+            move_number: game.move_count + 1,
+            board_hex_pair,
+            hex_pairs: player.hex_pairs,
+            progress: player.progress.clone(),
+            moves_in_turn: player.moves_in_turn,
+            bag: game.hex_pairs_in_bag.snapshot(),
+            player_to_move: game.player_to_move,
+            turn_count: game.turn_count,
+            passes_in_a_row: game.passes_in_a_row,
+            clock: game.clock.clone(),
+        }
+    }
+
+    // the clock goes back to the start of the player's turn, the time spent waiting for consent counts against them
+    pub fn restore(self, game: &mut Game, player: &mut Player, board: &mut Vec<BoardHex>) -> Result<(), &'static str> {
+        if player.uuid != self.player_uuid {
+            return Err("the placement was made by another player");
+        }
+        take_off_board(board, &self.board_hex_pair)?;
+        game.hex_pairs_in_bag.restore(self.bag);
+
+        player.hex_pairs = self.hex_pairs;
+        player.progress = self.progress;
+        player.moves_in_turn = self.moves_in_turn;
+        game.move_count = self.move_number - 1;
+        game.player_to_move = self.player_to_move;
+        game.turn_count = self.turn_count;
+        game.passes_in_a_row = self.passes_in_a_row;
+        game.clock = self.clock;
+        Ok(())
+    }
+}
+
+// the placement has to be the last one on the board
+fn take_off_board(board: &mut Vec<BoardHex>, board_hex_pair: &BoardHexPair) -> Result<(), &'static str> {
+    if !board.ends_with(board_hex_pair) {
+        return Err("the placement is not the last one on the board");
+    }
+    board.truncate(board.len() - board_hex_pair.len());
+    Ok(())
+}
+
+#[test]
+fn test_bag_and_board_are_restored_exactly() {
+    use crate::game::{refill_rack, HexPairsInBag};
+
+    let hex_pairs_in_bag = HexPairsInBag::new(7);
+    let mut hex_pairs: HexPairs = [None; 6];
+    refill_rack(&hex_pairs_in_bag, &mut hex_pairs);
+
+    hex_pairs[2] = None;
+    let snapshot = hex_pairs_in_bag.snapshot();
+    let mut after_placement = hex_pairs;
+    refill_rack(&hex_pairs_in_bag, &mut after_placement);
+    hex_pairs_in_bag.restore(snapshot);
+    let mut after_undo = hex_pairs;
+    refill_rack(&hex_pairs_in_bag, &mut after_undo);
+    assert_eq!(after_placement, after_undo);
+
+    let placed = [BoardHex { x: 1, y: 0, color: 1 }, BoardHex { x: 2, y: 0, color: 2 }];
+    let mut board = vec![BoardHex { x: 0, y: 0, color: 1 }, placed[0], placed[1]];
+    assert!(take_off_board(&mut board, &[placed[1], placed[0]]).is_err());
+    assert!(take_off_board(&mut board, &placed).is_ok());
+    assert_eq!(board, vec![BoardHex { x: 0, y: 0, color: 1 }]);
+}
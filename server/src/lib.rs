@@ -0,0 +1,6 @@
+// The rules of the game without the server around them, shared by the server and the tools in src/bin.
+
+pub mod types;
+pub mod game;
+pub mod util;
+pub mod rating;
@@ -14,24 +14,37 @@ use actix_cors::Cors;
 use sqlx::postgres::{PgPoolOptions};
 use sqlx::{FromRow, Pool, Postgres, Row};
 use serde::{Deserialize, Serialize};
-use crate::routes::lobby::{api_game_create, api_get_games, api_get_lobby_game, api_player_info, api_player_register, load_existing_games_from_database, load_existing_players_from_database};
+use crate::routes::lobby::{api_game_create, api_get_game_by_invite, api_get_games, api_get_lobby_game, api_player_info, api_player_register, load_existing_games_from_database, load_existing_players_from_database};
+use crate::routes::admin::{api_game_add_bot, api_game_cancel, api_game_kick_player, api_game_revoke_invite_code, api_game_transfer_admin, api_game_update};
+use crate::routes::leaderboard::api_get_leaderboard;
+use crate::routes::stats::api_player_stats;
+use crate::routes::analysis::api_game_analyze;
+use crate::routes::puzzle::{api_puzzle_create, api_puzzle_get, api_puzzle_solve};
+use crate::routes::history::{api_game_export, api_game_import, api_game_replay, api_games_history};
 use crate::ws::{websocket_handler, start_cleanup_task};
+use crate::ws::game::start_turn_task;
 use crate::types::{Boards, Games, Players};
 use futures_util::StreamExt;
 use crate::ws::rooms_state::RoomsState;
+use crate::ws::chat::Chat;
+use crate::ws::matchmaking::Matchmaking;
+use crate::ws::game_writes::GameWrites;
 
-mod types;
-mod game;
-mod util;
+use genial_server::{game, rating, types, util};
 mod routes;
 mod ws;
 
+// locks are always taken in this order: games map, game, players map, player, boards map, board.
+// a handler that needs an earlier lock after a later one drops what it holds first, game_writes is taken last
 pub struct AppState {
     postgres_pool: Pool<Postgres>,
     games: Games,
     players: Players,
     boards: Boards,
     rooms_state: Arc<RwLock<RoomsState>>,
+    chat: Chat,
+    matchmaking: Matchmaking,
+    game_writes: GameWrites,
 }
 
 #[actix_web::main]
@@ -49,21 +62,25 @@ async fn main() -> std::io::Result<()> {
         .await
         .expect("could not connect to database");
 
-    let cleanup_state = rooms_state.clone();
-    tokio::spawn(async move {
-        start_cleanup_task(cleanup_state).await;
-    });
-
     let app_data = web::Data::new(AppState {
         postgres_pool: pool.clone(),
         games: Games::default(),
         players: Players::default(),
         boards: Boards::default(),
         rooms_state: rooms_state,
+        chat: Chat::default(),
+        matchmaking: Matchmaking::default(),
+        game_writes: GameWrites::default(),
     });
 
-    load_existing_games_from_database(&app_data).await;
     load_existing_players_from_database(&app_data).await;
+    load_existing_games_from_database(&app_data).await;
+
+    let cleanup_state = app_data.clone();
+    tokio::spawn(async move {
+        start_cleanup_task(cleanup_state).await;
+    });
+    actix_web::rt::spawn(start_turn_task(app_data.clone()));
 
     HttpServer::new(move || {
         let cors = Cors::default()
@@ -79,9 +96,26 @@ async fn main() -> std::io::Result<()> {
             .wrap(cors)
             .route("/api/games", web::get().to(api_get_games))
             .route("/api/game", web::post().to(api_game_create))
+            .route("/api/game", web::patch().to(api_game_update))
+            .route("/api/game", web::delete().to(api_game_cancel))
+            .route("/api/game/kick", web::post().to(api_game_kick_player))
+            .route("/api/game/admin", web::post().to(api_game_transfer_admin))
+            .route("/api/game/bot", web::post().to(api_game_add_bot))
+            .route("/api/game/invite", web::post().to(api_get_game_by_invite))
+            .route("/api/game/invite/revoke", web::post().to(api_game_revoke_invite_code))
+            .route("/api/game/{uuid}/replay", web::get().to(api_game_replay))
+            .route("/api/game/{uuid}/export", web::get().to(api_game_export))
+            .route("/api/game/{uuid}/analyze", web::post().to(api_game_analyze))
+            .route("/api/game/import", web::post().to(api_game_import))
+            .route("/api/games/history", web::post().to(api_games_history))
             .route("/api/lobby_game", web::post().to(api_get_lobby_game))
             .route("/api/player/register", web::post().to(api_player_register))
             .route("/api/player/info", web::post().to(api_player_info))
+            .route("/api/player/{id}/stats", web::get().to(api_player_stats))
+            .route("/api/puzzle", web::post().to(api_puzzle_create))
+            .route("/api/puzzle/{id}", web::get().to(api_puzzle_get))
+            .route("/api/puzzle/{id}/solve", web::post().to(api_puzzle_solve))
+            .route("/api/leaderboard", web::get().to(api_get_leaderboard))
             .route("/ws/{user_id}", web::get().to(websocket_handler))
             .wrap(middleware::NormalizePath::trim())
     })
@@ -0,0 +1,43 @@
+// Pairwise Elo for multiplayer games: every player is compared with every other player by final
+// Ingenious ranking and the rating change is averaged over the opponents.
+
+const K_FACTOR: f64 = 32.0;
+
+pub fn expected_score(rating: f64, opponent_rating: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent_rating - rating) / 400.0))
+}
+
+// ratings and ranks are in the same order, rank 1 is the winner and equal ranks are ties
+pub fn calculate_rating_changes(ratings: &[f64], ranks: &[usize]) -> Vec<f64> {
+    let opponent_count = ratings.len().saturating_sub(1);
+    if opponent_count == 0 {
+        return vec![0.0; ratings.len()];
+    }
+
+    ratings.iter().zip(ranks).enumerate().map(|(i, (rating, rank))| {
+        let score_difference: f64 = ratings.iter().zip(ranks).enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, (opponent_rating, opponent_rank))| {
+                let actual_score = match rank.cmp(opponent_rank) {
+                    std::cmp::Ordering::Less => 1.0,
+                    std::cmp::Ordering::Equal => 0.5,
+                    std::cmp::Ordering::Greater => 0.0,
+                };
+                actual_score - expected_score(*rating, *opponent_rating)
+            })
+            .sum();
+
+        K_FACTOR * score_difference / opponent_count as f64
+    }).collect()
+}
+
+#[test]
+fn test_calculate_rating_changes() {
+    let initial_rating = 1500.0; // player.rating default
+    let changes = calculate_rating_changes(&[initial_rating, initial_rating], &[1, 2]);
+    assert_eq!(changes, vec![16.0, -16.0]);
+
+    let tie = calculate_rating_changes(&[initial_rating, initial_rating, initial_rating], &[1, 1, 3]);
+    assert_eq!(tie[0], tie[1]);
+    assert!((tie.iter().sum::<f64>()).abs() < 1e-9);
+}
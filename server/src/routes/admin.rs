@@ -0,0 +1,438 @@
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+use parking_lot::RwLock;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::game::bot::BotDifficulty;
+use crate::types::{GameStatus, Player, Progress};
+use crate::routes::lobby::{broadcast_lobby_game_change, collect_lobby_game_player_state, persist_game_status, remove_player_from_game, LobbyGameChange};
+use crate::util::{error_log, generate_invite_code, get_random_name};
+use crate::ws::lobby::{ws_ready_change, WsReadyChange};
+use crate::ws::broadcast_to_game_room;
+use crate::ws::view::broadcast_public_game_state;
+
+#[derive(Debug, PartialEq)]
+pub enum AdminActionError {
+    GameNotFound,
+    NotAdmin,
+    PlayerNotFound,
+    GameAlreadyStarted,
+    InvalidRequest(&'static str),
+}
+
+impl AdminActionError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            AdminActionError::GameNotFound => "game not found",
+            AdminActionError::NotAdmin => "only the game admin can do this",
+            AdminActionError::PlayerNotFound => "player is not seated in this game",
+            AdminActionError::GameAlreadyStarted => "game has already started",
+            AdminActionError::InvalidRequest(message) => message,
+        }
+    }
+
+    fn to_http_response(&self) -> HttpResponse {
+        let body = json!({ "status": "error", "message": self.message() });
+        match self {
+            AdminActionError::GameNotFound | AdminActionError::PlayerNotFound => HttpResponse::NotFound().json(body),
+            AdminActionError::NotAdmin => HttpResponse::Forbidden().json(body),
+            AdminActionError::GameAlreadyStarted => HttpResponse::Conflict().json(body),
+            AdminActionError::InvalidRequest(_) => HttpResponse::BadRequest().json(body),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct GameSettingsChange {
+    pub name: Option<String>,
+    pub board_size: Option<i32>,
+    pub show_progress: Option<bool>,
+}
+
+fn authorize_admin(data: &web::Data<AppState>, admin_uuid: &Uuid, game_uuid: &Uuid) -> Result<(), AdminActionError> {
+    match data.games.read().get(game_uuid) {
+        Some(game_rwlock) if game_rwlock.read().admin_uuid == *admin_uuid => Ok(()),
+        Some(_) => Err(AdminActionError::NotAdmin),
+        None => Err(AdminActionError::GameNotFound),
+    }
+}
+
+fn find_seated_player_uuid(data: &web::Data<AppState>, game_uuid: &Uuid, player_id: i32) -> Result<Uuid, AdminActionError> {
+    let games_read = data.games.read();
+    let game_read = games_read.get(game_uuid).ok_or(AdminActionError::GameNotFound)?.read();
+    let players_read = data.players.read();
+
+    game_read.players.iter()
+        .find(|uuid| players_read.get(uuid).is_some_and(|player_rwlock| player_rwlock.read().id == player_id))
+        .copied()
+        .ok_or(AdminActionError::PlayerNotFound)
+}
+
+fn is_game_started(data: &web::Data<AppState>, game_uuid: &Uuid) -> bool {
+    data.games.read().get(game_uuid).is_some_and(|game_rwlock| game_rwlock.read().status != GameStatus::Created)
+}
+
+pub async fn kick_player(data: &web::Data<AppState>, admin_uuid: &Uuid, game_uuid: &Uuid, target_player_id: i32) -> Result<(), AdminActionError> {
+    authorize_admin(data, admin_uuid, game_uuid)?;
+    if is_game_started(data, game_uuid) {
+        return Err(AdminActionError::GameAlreadyStarted);
+    }
+    let target_uuid = find_seated_player_uuid(data, game_uuid, target_player_id)?;
+    if target_uuid == *admin_uuid {
+        return Err(AdminActionError::InvalidRequest("the admin cannot kick themselves"));
+    }
+
+    // broadcast before removing the player from the room so the kicked player is notified as well
+    broadcast_to_game_room(data, game_uuid, json!({
+        "type": "player_kicked",
+        "data": {
+            "game_uuid": game_uuid,
+            "player_id": target_player_id,
+        }
+    }));
+
+    remove_player_from_game(data, &target_uuid, game_uuid);
+    broadcast_lobby_game_change(data, LobbyGameChange::Updated, game_uuid);
+
+    Ok(())
+}
+
+pub async fn transfer_admin(data: &web::Data<AppState>, admin_uuid: &Uuid, game_uuid: &Uuid, target_player_id: i32) -> Result<(), AdminActionError> {
+    authorize_admin(data, admin_uuid, game_uuid)?;
+    let target_uuid = find_seated_player_uuid(data, game_uuid, target_player_id)?;
+
+    if let Some(game_rwlock) = data.games.read().get(game_uuid) {
+        let mut game_write = game_rwlock.write();
+        game_write.admin_uuid = target_uuid;
+        game_write.admin_id = target_player_id;
+    }
+
+    let query_result = sqlx::query("UPDATE game SET admin_uuid = $1 WHERE uuid = $2")
+        .bind(target_uuid.to_string())
+        .bind(game_uuid.to_string())
+        .execute(&data.postgres_pool)
+        .await;
+    if let Err(error) = query_result {
+        error_log(format!("database error (transfer_admin) {}", error));
+    }
+
+    broadcast_to_game_room(data, game_uuid, json!({
+        "type": "game_admin_changed",
+        "data": {
+            "game_uuid": game_uuid,
+            "admin_id": target_player_id,
+        }
+    }));
+    broadcast_lobby_game_change(data, LobbyGameChange::Updated, game_uuid);
+
+    Ok(())
+}
+
+pub async fn update_game_settings(data: &web::Data<AppState>, admin_uuid: &Uuid, game_uuid: &Uuid, settings: &GameSettingsChange) -> Result<(), AdminActionError> {
+    authorize_admin(data, admin_uuid, game_uuid)?;
+    if is_game_started(data, game_uuid) {
+        return Err(AdminActionError::GameAlreadyStarted);
+    }
+    if settings.name.as_ref().is_some_and(|name| name.trim().is_empty() || name.chars().count() > 255) {
+        return Err(AdminActionError::InvalidRequest("game name must be between 1 and 255 characters"));
+    }
+    if settings.board_size.is_some_and(|board_size| !(6..=8).contains(&board_size)) {
+        return Err(AdminActionError::InvalidRequest("board size must be between 6 and 8"));
+    }
+
+    let (name, board_size, show_progress) = match data.games.read().get(game_uuid) {
+        Some(game_rwlock) => {
+            let mut game_write = game_rwlock.write();
+            if let Some(name) = &settings.name {
+                game_write.name = name.trim().to_string();
+            }
+            if let Some(board_size) = settings.board_size {
+                game_write.board_size = board_size;
+            }
+            if let Some(show_progress) = settings.show_progress {
+                game_write.show_progress = show_progress;
+            }
+            (game_write.name.clone(), game_write.board_size, game_write.show_progress)
+        }
+        None => return Err(AdminActionError::GameNotFound),
+    };
+
+    let query_result = sqlx::query("UPDATE game SET name = $1, board_size = $2, show_progress = $3 WHERE uuid = $4")
+        .bind(&name)
+        .bind(board_size)
+        .bind(show_progress)
+        .bind(game_uuid.to_string())
+        .execute(&data.postgres_pool)
+        .await;
+    if let Err(error) = query_result {
+        error_log(format!("database error (update_game_settings) {}", error));
+    }
+
+    broadcast_to_game_room(data, game_uuid, json!({
+        "type": "game_settings_updated",
+        "data": {
+            "game_uuid": game_uuid,
+            "name": name,
+            "board_size": board_size,
+            "show_progress": show_progress,
+        }
+    }));
+    broadcast_lobby_game_change(data, LobbyGameChange::Updated, game_uuid);
+
+    Ok(())
+}
+
+// a game that has not started is deleted, a running game is abandoned so its moves, draws and seats stay on record
+pub async fn cancel_game(data: &web::Data<AppState>, admin_uuid: &Uuid, game_uuid: &Uuid) -> Result<(), AdminActionError> {
+    authorize_admin(data, admin_uuid, game_uuid)?;
+
+    let abandon = match data.games.read().get(game_uuid) {
+        Some(game_rwlock) => {
+            let mut game_write = game_rwlock.write();
+            match game_write.status {
+                GameStatus::Created => false,
+                status if status.is_running() => {
+                    if let Err(error) = game_write.transition_to(GameStatus::Abandoned) {
+                        error_log(error);
+                        return Err(AdminActionError::InvalidRequest("the game can not be cancelled"));
+                    }
+                    game_write.player_to_move = None;
+                    true
+                }
+                _ => return Err(AdminActionError::InvalidRequest("the game is already over")),
+            }
+        }
+        None => return Err(AdminActionError::GameNotFound),
+    };
+
+    if abandon {
+        persist_game_status(data, game_uuid, GameStatus::Abandoned);
+        data.game_writes.lock().close(game_uuid);
+        broadcast_public_game_state(data, game_uuid, "game_state");
+        broadcast_lobby_game_change(data, LobbyGameChange::Updated, game_uuid);
+        return Ok(());
+    }
+
+    broadcast_to_game_room(data, game_uuid, json!({
+        "type": "game_cancelled",
+        "data": {
+            "game_uuid": game_uuid,
+        }
+    }));
+
+    let (players, spectators) = match data.games.read().get(game_uuid) {
+        Some(game_rwlock) => {
+            let game_read = game_rwlock.read();
+            (game_read.players.clone(), game_read.spectators.clone())
+        }
+        None => return Err(AdminActionError::GameNotFound),
+    };
+    players.iter().chain(spectators.iter()).for_each(|player_uuid| {
+        remove_player_from_game(data, player_uuid, game_uuid);
+    });
+
+    // the lobby change needs the game to tell whether it was public
+    broadcast_lobby_game_change(data, LobbyGameChange::Removed, game_uuid);
+    data.games.write().remove(game_uuid);
+    data.boards.write().remove(game_uuid);
+    data.game_writes.lock().close(game_uuid);
+
+    let query_result = sqlx::query("DELETE FROM game WHERE uuid = $1")
+        .bind(game_uuid.to_string())
+        .execute(&data.postgres_pool)
+        .await;
+    if let Err(error) = query_result {
+        error_log(format!("database error (cancel_game) {}", error));
+    }
+
+    Ok(())
+}
+
+// replaces the invite code of a private game, the previous code stops working
+pub async fn regenerate_invite_code(data: &web::Data<AppState>, admin_uuid: &Uuid, game_uuid: &Uuid) -> Result<String, AdminActionError> {
+    authorize_admin(data, admin_uuid, game_uuid)?;
+
+    let invite_code = generate_invite_code();
+    match data.games.read().get(game_uuid) {
+        Some(game_rwlock) => {
+            let mut game_write = game_rwlock.write();
+            if game_write.public {
+                return Err(AdminActionError::InvalidRequest("public games do not have an invite code"));
+            }
+            game_write.invite_code = Some(invite_code.clone());
+        }
+        None => return Err(AdminActionError::GameNotFound),
+    }
+
+    let query_result = sqlx::query("UPDATE game SET invite_code = $1 WHERE uuid = $2")
+        .bind(&invite_code)
+        .bind(game_uuid.to_string())
+        .execute(&data.postgres_pool)
+        .await;
+    if let Err(error) = query_result {
+        error_log(format!("database error (regenerate_invite_code) {}", error));
+    }
+
+    Ok(invite_code)
+}
+
+// seats a computer player, it is always ready so the game starts once the other seats are ready
+pub async fn add_bot(data: &web::Data<AppState>, admin_uuid: &Uuid, game_uuid: &Uuid, difficulty: BotDifficulty) -> Result<i32, AdminActionError> {
+    authorize_admin(data, admin_uuid, game_uuid)?;
+    if is_game_started(data, game_uuid) {
+        return Err(AdminActionError::GameAlreadyStarted);
+    }
+    let has_free_seat = match data.games.read().get(game_uuid) {
+        Some(game_rwlock) => {
+            let game_read = game_rwlock.read();
+            if game_read.rated {
+                return Err(AdminActionError::InvalidRequest("bots cannot play rated games"));
+            }
+            game_read.players.len() < game_read.player_count as usize
+        }
+        None => return Err(AdminActionError::GameNotFound),
+    };
+    if !has_free_seat {
+        return Err(AdminActionError::InvalidRequest("game is full"));
+    }
+
+    let bot_uuid = Uuid::new_v4();
+    let name = format!("{} ({} bot)", get_random_name(), difficulty.as_str());
+    let insert_result: Result<(i32, f64), sqlx::Error> = sqlx::query_as("INSERT INTO player (uuid, name, bot, game_uuid) VALUES ($1, $2, $3, $4) RETURNING id, rating")
+        .bind(bot_uuid.to_string())
+        .bind(&name)
+        .bind(difficulty.as_str())
+        .bind(game_uuid.to_string())
+        .fetch_one(&data.postgres_pool)
+        .await;
+    let (bot_id, rating) = match insert_result {
+        Ok(inserted) => inserted,
+        Err(error) => {
+            error_log(format!("database error (add_bot) {}", error));
+            return Err(AdminActionError::InvalidRequest("could not create the bot"));
+        }
+    };
+
+    data.players.write().insert(bot_uuid, Arc::new(RwLock::new(Player {
+        name,
+        ready: true,
+        uuid: bot_uuid,
+        id: bot_id,
+        game_uuid: Some(*game_uuid),
+        hex_pairs: [None, None, None, None, None, None],
+        moves_in_turn: 0,
+        progress: Progress::new(),
+        moderator: false,
+        rating,
+        bot: Some(difficulty),
+    })));
+
+    // the seat is checked again under the game lock, a player may have joined while the bot was stored
+    let seated = match data.games.read().get(game_uuid) {
+        Some(game_rwlock) => {
+            let mut game_write = game_rwlock.write();
+            let seated = game_write.status == GameStatus::Created && game_write.players.len() < game_write.player_count as usize;
+            if seated {
+                game_write.players.push(bot_uuid);
+                game_write.assign_team(&bot_uuid);
+            }
+            seated
+        }
+        None => false,
+    };
+    if !seated {
+        data.players.write().remove(&bot_uuid);
+        return Err(AdminActionError::InvalidRequest("game is full"));
+    }
+
+    broadcast_to_game_room(data, game_uuid, json!({
+        "type": "player_joined",
+        "data": {
+            "games": json!({
+                &game_uuid.to_string(): {
+                    "players": collect_lobby_game_player_state(data, game_uuid),
+                }
+            })
+        }
+    }));
+    broadcast_lobby_game_change(data, LobbyGameChange::Updated, game_uuid);
+
+    ws_ready_change(data, &WsReadyChange { player_uuid: bot_uuid, game_uuid: *game_uuid, ready: true }).await;
+
+    Ok(bot_id)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiGameAdminSchema {
+    pub gameUuid: Uuid,
+    pub playerUuid: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiGameAdminTargetSchema {
+    pub gameUuid: Uuid,
+    pub playerUuid: Uuid,
+    pub targetPlayerId: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiGameUpdateSchema {
+    pub gameUuid: Uuid,
+    pub playerUuid: Uuid,
+    pub name: Option<String>,
+    pub boardSize: Option<i32>,
+    pub showProgress: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiGameAddBotSchema {
+    pub gameUuid: Uuid,
+    pub playerUuid: Uuid,
+    #[serde(default)]
+    pub difficulty: BotDifficulty,
+}
+
+fn admin_action_response(result: Result<(), AdminActionError>) -> HttpResponse {
+    match result {
+        Ok(()) => HttpResponse::Ok().json(json!({ "status": "success" })),
+        Err(error) => error.to_http_response(),
+    }
+}
+
+pub async fn api_game_kick_player(body: web::Json<ApiGameAdminTargetSchema>, data: web::Data<AppState>) -> HttpResponse {
+    admin_action_response(kick_player(&data, &body.playerUuid, &body.gameUuid, body.targetPlayerId).await)
+}
+
+pub async fn api_game_transfer_admin(body: web::Json<ApiGameAdminTargetSchema>, data: web::Data<AppState>) -> HttpResponse {
+    admin_action_response(transfer_admin(&data, &body.playerUuid, &body.gameUuid, body.targetPlayerId).await)
+}
+
+pub async fn api_game_update(body: web::Json<ApiGameUpdateSchema>, data: web::Data<AppState>) -> HttpResponse {
+    let settings = GameSettingsChange {
+        name: body.name.clone(),
+        board_size: body.boardSize,
+        show_progress: body.showProgress,
+    };
+    admin_action_response(update_game_settings(&data, &body.playerUuid, &body.gameUuid, &settings).await)
+}
+
+pub async fn api_game_revoke_invite_code(body: web::Json<ApiGameAdminSchema>, data: web::Data<AppState>) -> HttpResponse {
+    match regenerate_invite_code(&data, &body.playerUuid, &body.gameUuid).await {
+        Ok(invite_code) => HttpResponse::Ok().json(json!({ "status": "success", "inviteCode": invite_code })),
+        Err(error) => error.to_http_response(),
+    }
+}
+
+pub async fn api_game_cancel(body: web::Json<ApiGameAdminSchema>, data: web::Data<AppState>) -> HttpResponse {
+    admin_action_response(cancel_game(&data, &body.playerUuid, &body.gameUuid).await)
+}
+
+pub async fn api_game_add_bot(body: web::Json<ApiGameAddBotSchema>, data: web::Data<AppState>) -> HttpResponse {
+    match add_bot(&data, &body.playerUuid, &body.gameUuid, body.difficulty).await {
+        Ok(bot_id) => HttpResponse::Ok().json(json!({ "status": "success", "botId": bot_id })),
+        Err(error) => error.to_http_response(),
+    }
+}
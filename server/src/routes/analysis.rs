@@ -0,0 +1,81 @@
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::game::analysis::{analyze_position, PositionAnalysis};
+use crate::types::GameStatus;
+
+const DEFAULT_HINT_COUNT: usize = 5;
+const MAX_HINT_COUNT: usize = 20;
+
+#[derive(Debug, PartialEq)]
+pub enum HintError {
+    GameNotFound,
+    PlayerNotFound,
+    RatedGame,
+    GameNotInProgress,
+}
+
+impl HintError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            HintError::GameNotFound => "game not found",
+            HintError::PlayerNotFound => "player is not seated in this game",
+            HintError::RatedGame => "hints are disabled in rated games",
+            HintError::GameNotInProgress => "game is not in progress",
+        }
+    }
+
+    fn to_http_response(&self) -> HttpResponse {
+        let body = json!({ "status": "error", "message": self.message() });
+        match self {
+            HintError::GameNotFound | HintError::PlayerNotFound => HttpResponse::NotFound().json(body),
+            HintError::RatedGame => HttpResponse::Forbidden().json(body),
+            HintError::GameNotInProgress => HttpResponse::Conflict().json(body),
+        }
+    }
+}
+
+// ranks the placements of the player's rack on the current board, only in unrated games
+pub fn analyze_for_player(data: &web::Data<AppState>, game_uuid: &Uuid, player_uuid: &Uuid, count: Option<usize>) -> Result<PositionAnalysis, HintError> {
+    let (board_size, rules) = match data.games.read().get(game_uuid) {
+        Some(game_rwlock) => {
+            let game_read = game_rwlock.read();
+            if game_read.rated {
+                return Err(HintError::RatedGame);
+            }
+            if game_read.status != GameStatus::InProgress {
+                return Err(HintError::GameNotInProgress);
+            }
+            if !game_read.players.contains(player_uuid) {
+                return Err(HintError::PlayerNotFound);
+            }
+            (game_read.board_size, game_read.variant.rules())
+        }
+        None => return Err(HintError::GameNotFound),
+    };
+
+    let (hex_pairs, progress) = data.players.read().get(player_uuid).map(|player_rwlock| {
+        let player_read = player_rwlock.read();
+        (player_read.hex_pairs, player_read.progress.clone())
+    }).ok_or(HintError::PlayerNotFound)?;
+    let board = data.boards.read().get(game_uuid).map(|board_rwlock| board_rwlock.read().clone()).ok_or(HintError::GameNotFound)?;
+
+    let count = count.unwrap_or(DEFAULT_HINT_COUNT).clamp(1, MAX_HINT_COUNT);
+    Ok(analyze_position(rules, &board, board_size, &progress, &hex_pairs, count))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiGameAnalyzeSchema {
+    pub playerUuid: Uuid,
+    pub count: Option<usize>,
+}
+
+pub async fn api_game_analyze(path: web::Path<Uuid>, body: web::Json<ApiGameAnalyzeSchema>, data: web::Data<AppState>) -> HttpResponse {
+    match analyze_for_player(&data, &path.into_inner(), &body.playerUuid, body.count) {
+        Ok(analysis) => HttpResponse::Ok().json(json!({ "status": "success", "analysis": analysis })),
+        Err(error) => error.to_http_response(),
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::Error;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::game::replay_placements;
+use crate::game::notation::{GameRecord, NotationMove, NotationTurn};
+use crate::types::{BoardHex, BoardHexPair, Color, GameStatus};
+use crate::util::error_log;
+
+const HISTORY_PAGE_SIZE: i64 = 20;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiGamesHistorySchema {
+    pub playerUuid: Uuid,
+    pub page: Option<i64>, // starts at 1
+}
+
+#[derive(Serialize, Debug)]
+pub struct ApiGameHistoryEntry {
+    pub uuid: String,
+    pub name: String,
+    pub boardSize: i32,
+    pub rated: bool,
+    pub rank: i32,
+    pub endedAt: i64, // unix timestamp in seconds
+    pub players: Vec<serde_json::Value>,
+}
+
+#[derive(sqlx::FromRow)]
+struct HistoryGameRow {
+    uuid: String,
+    name: String,
+    board_size: i32,
+    rated: bool,
+    rank: i32,
+    ended_at: i64,
+}
+
+fn database_error_response(source: &str, error: Error) -> HttpResponse {
+    error_log(format!("database error ({}) {}", source, error));
+    HttpResponse::InternalServerError().json(json!({ "status": "error" }))
+}
+
+pub async fn api_games_history(body: web::Json<ApiGamesHistorySchema>, data: web::Data<AppState>) -> HttpResponse {
+    let page = body.page.unwrap_or(1).max(1);
+
+    let games: Result<Vec<HistoryGameRow>, Error> = sqlx::query_as(r#"
+        SELECT g.uuid, g.name, g.board_size, g.rated, r.rank, extract(epoch FROM r.created_at)::bigint AS ended_at
+        FROM game_result r
+        JOIN game g ON g.uuid = r.game_uuid
+        WHERE r.player_uuid = $1
+        ORDER BY r.created_at DESC
+        LIMIT $2 OFFSET $3
+    "#)
+        .bind(body.playerUuid.to_string())
+        .bind(HISTORY_PAGE_SIZE)
+        .bind((page - 1) * HISTORY_PAGE_SIZE)
+        .fetch_all(&data.postgres_pool)
+        .await;
+    let games = match games {
+        Ok(games) => games,
+        Err(error) => return database_error_response("api_games_history", error),
+    };
+
+    let game_uuids: Vec<String> = games.iter().map(|game| game.uuid.clone()).collect();
+    let participants: Result<Vec<(String, i32, String, i32)>, Error> = sqlx::query_as(r#"
+        SELECT r.game_uuid, p.id, p.name, r.rank
+        FROM game_result r
+        JOIN player p ON p.uuid = r.player_uuid
+        WHERE r.game_uuid = ANY($1)
+        ORDER BY r.rank
+    "#)
+        .bind(&game_uuids)
+        .fetch_all(&data.postgres_pool)
+        .await;
+    let participants = match participants {
+        Ok(participants) => participants,
+        Err(error) => return database_error_response("api_games_history", error),
+    };
+
+    let history: Vec<ApiGameHistoryEntry> = games.into_iter().map(|game| ApiGameHistoryEntry {
+        players: participants.iter()
+            .filter(|(game_uuid, ..)| *game_uuid == game.uuid)
+            .map(|(_, id, name, rank)| json!({ "id": id, "name": name, "rank": rank }))
+            .collect(),
+        uuid: game.uuid,
+        name: game.name,
+        boardSize: game.board_size,
+        rated: game.rated,
+        rank: game.rank,
+        endedAt: game.ended_at,
+    }).collect();
+
+    HttpResponse::Ok().json(json!({ "status": "success", "page": page, "data": history }))
+}
+
+#[derive(sqlx::FromRow)]
+struct ReplayMoveRow {
+    player_uuid: String,
+    move_number: i32,
+    hex1_x: i32,
+    hex1_y: i32,
+    hex1_color: i32,
+    hex2_x: i32,
+    hex2_y: i32,
+    hex2_color: i32,
+    ingenious_count: i32,
+    turn_number: Option<i32>, // missing for games played before turns were stored
+}
+
+#[derive(sqlx::FromRow)]
+struct ReplayDrawRow {
+    player_uuid: String,
+    move_number: i32,
+    rack_index: i32,
+    color1: i32,
+    color2: i32,
+}
+
+impl ReplayMoveRow {
+    fn board_hex_pair(&self) -> BoardHexPair {
+        [
+            BoardHex { x: self.hex1_x as i8, y: self.hex1_y as i8, color: self.hex1_color as Color },
+            BoardHex { x: self.hex2_x as i8, y: self.hex2_y as i8, color: self.hex2_color as Color },
+        ]
+    }
+}
+
+struct FinishedGame {
+    name: String,
+    board_size: i32,
+    seed: u64,
+    seats: Vec<(String, i32, String)>, // (player uuid, id, name) in move order
+    ranks: Vec<(String, i32)>, // (player uuid, rank)
+    moves: Vec<ReplayMoveRow>,
+    draws: Vec<ReplayDrawRow>,
+    passes: Vec<(String, i32)>, // (player uuid, turn number)
+}
+
+async fn load_finished_game(data: &web::Data<AppState>, game_uuid: &str, source: &str) -> Result<FinishedGame, HttpResponse> {
+    let game: Result<Option<(String, i32, String, i64)>, Error> = sqlx::query_as("SELECT name, board_size, status, seed FROM game WHERE uuid = $1")
+        .bind(game_uuid)
+        .fetch_optional(&data.postgres_pool)
+        .await;
+    let (name, board_size, status, seed) = match game {
+        Ok(Some(game)) => game,
+        Ok(None) => return Err(HttpResponse::NotFound().json(json!({ "status": "error", "message": "game not found" }))),
+        Err(error) => return Err(database_error_response(source, error)),
+    };
+    // running games are not replayable, that would reveal the upcoming draws
+    if status != GameStatus::Ended.as_str() {
+        return Err(HttpResponse::Conflict().json(json!({ "status": "error", "message": "game has not ended" })));
+    }
+
+    let seats: Result<Vec<(String, i32, String)>, Error> = sqlx::query_as(r#"
+        SELECT s.player_uuid, p.id, p.name FROM game_seat s JOIN player p ON p.uuid = s.player_uuid WHERE s.game_uuid = $1 ORDER BY s.seat
+    "#)
+        .bind(game_uuid)
+        .fetch_all(&data.postgres_pool)
+        .await;
+    let ranks: Result<Vec<(String, i32)>, Error> = sqlx::query_as("SELECT player_uuid, rank FROM game_result WHERE game_uuid = $1")
+        .bind(game_uuid)
+        .fetch_all(&data.postgres_pool)
+        .await;
+    let moves: Result<Vec<ReplayMoveRow>, Error> = sqlx::query_as(r#"
+        SELECT player_uuid, move_number, hex1_x, hex1_y, hex1_color, hex2_x, hex2_y, hex2_color, ingenious_count, turn_number
+        FROM game_move WHERE game_uuid = $1 ORDER BY move_number
+    "#)
+        .bind(game_uuid)
+        .fetch_all(&data.postgres_pool)
+        .await;
+    let draws: Result<Vec<ReplayDrawRow>, Error> = sqlx::query_as(r#"
+        SELECT player_uuid, move_number, rack_index, color1, color2 FROM game_draw WHERE game_uuid = $1 ORDER BY move_number, id
+    "#)
+        .bind(game_uuid)
+        .fetch_all(&data.postgres_pool)
+        .await;
+
+    let passes: Result<Vec<(String, i32)>, Error> = sqlx::query_as("SELECT player_uuid, turn_number FROM game_pass WHERE game_uuid = $1 ORDER BY turn_number")
+        .bind(game_uuid)
+        .fetch_all(&data.postgres_pool)
+        .await;
+
+    match (seats, ranks, moves, draws, passes) {
+        (Ok(seats), Ok(ranks), Ok(moves), Ok(draws), Ok(passes)) => Ok(FinishedGame { name, board_size, seed: seed as u64, seats, ranks, moves, draws, passes }),
+        (Err(error), ..) | (_, Err(error), ..) | (_, _, Err(error), ..) | (_, _, _, Err(error), _) | (.., Err(error)) => Err(database_error_response(source, error)),
+    }
+}
+
+pub async fn api_game_replay(path: web::Path<Uuid>, data: web::Data<AppState>) -> HttpResponse {
+    let game_uuid = path.into_inner().to_string();
+    let game = match load_finished_game(&data, &game_uuid, "api_game_replay").await {
+        Ok(game) => game,
+        Err(response) => return response,
+    };
+    let seats = &game.seats;
+
+    // player uuids are secret, the replay only identifies players by id
+    let seat_by_uuid: HashMap<&str, usize> = seats.iter().enumerate().map(|(seat, (player_uuid, ..))| (player_uuid.as_str(), seat)).collect();
+    let player_id = |player_uuid: &str| seat_by_uuid.get(player_uuid).map(|seat| seats[*seat].1);
+    let draws_after = |move_number: i32| game.draws.iter()
+        .filter(|draw| draw.move_number == move_number)
+        .map(|draw| json!({
+            "playerId": player_id(&draw.player_uuid),
+            "rackIndex": draw.rack_index,
+            "hexPair": [draw.color1, draw.color2],
+        }))
+        .collect::<Vec<serde_json::Value>>();
+
+    let moves: Vec<&ReplayMoveRow> = game.moves.iter().filter(|row| seat_by_uuid.contains_key(row.player_uuid.as_str())).collect();
+    let placements: Vec<(usize, BoardHexPair)> = moves.iter().map(|row| (seat_by_uuid[row.player_uuid.as_str()], row.board_hex_pair())).collect();
+    let states = replay_placements(&placements, seats.len());
+
+    let steps: Vec<serde_json::Value> = moves.iter().zip(states).map(|(row, (board, progresses))| json!({
+        "moveNumber": row.move_number,
+        "playerId": player_id(&row.player_uuid),
+        "hexPair": row.board_hex_pair(),
+        "ingeniousCount": row.ingenious_count,
+        "draws": draws_after(row.move_number),
+        "board": board,
+        "progress": seats.iter().zip(progresses).map(|((_, id, _), progress)| (id.to_string(), json!(progress))).collect::<serde_json::Map<String, serde_json::Value>>(),
+    })).collect();
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "data": {
+            "uuid": game_uuid,
+            "name": game.name,
+            "boardSize": game.board_size,
+            "seed": game.seed.to_string(), // a string because the seed does not fit in a javascript number
+            "seats": seats.iter().map(|(_, id, name)| json!({ "id": id, "name": name })).collect::<Vec<serde_json::Value>>(),
+            "initialDraws": draws_after(0),
+            "steps": steps,
+        }
+    }))
+}
+
+// a finished game in the text notation, see game::notation
+pub async fn api_game_export(path: web::Path<Uuid>, data: web::Data<AppState>) -> HttpResponse {
+    let game_uuid = path.into_inner().to_string();
+    let game = match load_finished_game(&data, &game_uuid, "api_game_export").await {
+        Ok(game) => game,
+        Err(response) => return response,
+    };
+
+    let seat_of = |player_uuid: &str| game.seats.iter().position(|(uuid, ..)| uuid == player_uuid);
+
+    // (turn number, seat, moves), games from before turns were stored group consecutive placements of a player
+    let mut turns: Vec<(i32, usize, Vec<NotationMove>)> = Vec::new();
+    let legacy = game.moves.iter().any(|row| row.turn_number.is_none());
+    for row in &game.moves {
+        let Some(seat) = seat_of(&row.player_uuid) else {
+            continue;
+        };
+        let turn_number = row.turn_number.unwrap_or_else(|| match turns.last() {
+            Some((turn_number, last_seat, _)) if *last_seat == seat => *turn_number,
+            _ => turns.len() as i32 + 1,
+        });
+        let notation_move = NotationMove::Placement { hex_pair: row.board_hex_pair(), bonus: row.ingenious_count as u8 };
+        match turns.last_mut() {
+            Some((last_turn_number, _, moves)) if *last_turn_number == turn_number => moves.push(notation_move),
+            _ => turns.push((turn_number, seat, vec![notation_move])),
+        }
+    }
+    if !legacy {
+        turns.extend(game.passes.iter().filter_map(|(player_uuid, turn_number)| Some((*turn_number, seat_of(player_uuid)?, Vec::new()))));
+        turns.sort_by_key(|(turn_number, ..)| *turn_number);
+    }
+
+    let record = GameRecord {
+        name: game.name,
+        board_size: game.board_size,
+        seed: game.seed,
+        seats: game.seats.iter().map(|(_, _, name)| name.clone()).collect(),
+        ranks: game.seats.iter()
+            .map(|(player_uuid, ..)| game.ranks.iter().find(|(uuid, _)| uuid == player_uuid).map(|(_, rank)| *rank as usize))
+            .collect(),
+        turns: turns.into_iter().map(|(_, seat, moves)| NotationTurn { seat, moves }).collect(),
+    };
+
+    HttpResponse::Ok().content_type("text/plain; charset=utf-8").body(record.to_string())
+}
+
+// parses a game in the text notation and replays it, nothing is stored
+pub async fn api_game_import(body: String) -> HttpResponse {
+    let record: GameRecord = match body.parse() {
+        Ok(record) => record,
+        Err(error) => return HttpResponse::BadRequest().json(json!({ "status": "error", "message": error })),
+    };
+
+    let moves = record.moves();
+    let placements: Vec<(usize, BoardHexPair)> = moves.iter().filter_map(|(seat, notation_move)| match notation_move {
+        NotationMove::Placement { hex_pair, .. } => Some((*seat, *hex_pair)),
+        NotationMove::Swap => None,
+    }).collect();
+    let states = replay_placements(&placements, record.seats.len());
+
+    let steps: Vec<serde_json::Value> = placements.iter().zip(states).map(|((seat, hex_pair), (board, progresses))| json!({
+        "seat": seat,
+        "hexPair": hex_pair,
+        "board": board,
+        "progress": progresses,
+    })).collect();
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "data": {
+            "name": record.name,
+            "boardSize": record.board_size,
+            "seed": record.seed.to_string(),
+            "seats": record.seats,
+            "ranks": record.ranks,
+            "steps": steps,
+        }
+    }))
+}
@@ -0,0 +1,83 @@
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::Error;
+
+use crate::AppState;
+use crate::util::error_log;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 100;
+
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardSort {
+    #[default]
+    Rating,
+    Wins,
+    GamesPlayed,
+}
+
+impl LeaderboardSort {
+    fn order_by(&self) -> &'static str {
+        match self {
+            LeaderboardSort::Rating => "p.rating DESC",
+            LeaderboardSort::Wins => "wins DESC, p.rating DESC",
+            LeaderboardSort::GamesPlayed => "games_played DESC, p.rating DESC",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ApiLeaderboardQuery {
+    pub sort: Option<LeaderboardSort>,
+    pub page: Option<i64>, // starts at 1
+    pub pageSize: Option<i64>,
+    pub days: Option<i32>, // only count games finished within this many days, all time when missing
+}
+
+#[derive(Serialize, Deserialize, Debug, sqlx::FromRow)]
+pub struct ApiLeaderboardEntry {
+    pub id: i32,
+    pub name: String,
+    pub rating: f64,
+    pub wins: i64,
+    pub games_played: i64,
+}
+
+pub async fn api_get_leaderboard(query: web::Query<ApiLeaderboardQuery>, data: web::Data<AppState>) -> HttpResponse {
+    let sort = query.sort.unwrap_or_default();
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.pageSize.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    // only players that finished at least one game in the window are ranked, bots never are
+    let sql = format!(r#"
+        SELECT p.id, p.name, p.rating, count(r.game_uuid) FILTER (WHERE r.rank = 1) AS wins, count(r.game_uuid) AS games_played
+        FROM player p
+        JOIN game_result r ON r.player_uuid = p.uuid AND ($1::integer IS NULL OR r.created_at > now() - make_interval(days => $1))
+        WHERE p.bot IS NULL
+        GROUP BY p.id
+        ORDER BY {}, p.id
+        LIMIT $2 OFFSET $3
+    "#, sort.order_by());
+
+    let rows: Result<Vec<ApiLeaderboardEntry>, Error> = sqlx::query_as(&sql)
+        .bind(query.days)
+        .bind(page_size)
+        .bind((page - 1) * page_size)
+        .fetch_all(&data.postgres_pool)
+        .await;
+
+    match rows {
+        Ok(leaderboard) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "page": page,
+            "pageSize": page_size,
+            "data": leaderboard,
+        })),
+        Err(error) => {
+            error_log(format!("database error (api_get_leaderboard) {}", error));
+            HttpResponse::InternalServerError().json(json!({ "status": "error" }))
+        }
+    }
+}
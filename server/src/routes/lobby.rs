@@ -1,4 +1,5 @@
 use std::io::prelude::*;
+use std::collections::HashMap;
 use std::sync::{Arc};
 use parking_lot::RwLock;
 use actix_web::{Responder, web, HttpResponse};
@@ -7,10 +8,12 @@ use serde_json::json;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::types::{Game, Player, Progress};
+use crate::types::{DisconnectedSeatAction, Game, GameStatus, GameVariant, Player, Progress};
 use crate::AppState;
-use crate::util::{error_log, get_random_name};
-use crate::game::HexPairsInBag;
+use crate::util::{error_log, generate_invite_code, get_random_name};
+use crate::game::bot::BotDifficulty;
+use crate::game::clock::{TimeControl, TimeoutAction};
+use crate::ws::rooms_state::LOBBY_ROOM;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CreateGameSchema {
@@ -19,6 +22,13 @@ pub struct CreateGameSchema {
     pub playerCount: i32,
     pub showProgress: bool,
     pub playerUuid: Uuid,
+    pub allowSpectators: Option<bool>,
+    pub public: Option<bool>,
+    pub rated: Option<bool>,
+    pub timeControl: Option<TimeControl>,
+    pub disconnectedSeat: Option<DisconnectedSeatAction>,
+    pub teamMode: Option<bool>,
+    pub variant: Option<GameVariant>,
 }
 
 pub async fn load_existing_games_from_database(data: &web::Data<AppState>) {
@@ -31,25 +41,80 @@ pub async fn load_existing_games_from_database(data: &web::Data<AppState>) {
         show_progress: bool,
         status: String,
         admin_uuid: String,
+        allow_spectators: bool,
+        public: bool,
+        invite_code: Option<String>,
+        rated: bool,
+        seed: i64,
+        move_limit_seconds: Option<i32>,
+        time_bank_seconds: Option<i32>,
+        increment_seconds: i32,
+        timeout_action: String,
+        disconnected_seat: String,
+        team_mode: bool,
+        variant: String,
     }
-    let query = "SELECT admin_uuid, name, uuid, board_size, player_count, show_progress, status FROM game";
+    // boards and hex pairs are only kept in memory, so games that were running when the server stopped can not be resumed
+    let abandon_query = "UPDATE game SET status = $1 WHERE status IN ($2, $3, $4)";
+    let abandon_result = sqlx::query(abandon_query)
+        .bind(GameStatus::Abandoned.as_str())
+        .bind(GameStatus::Starting.as_str())
+        .bind(GameStatus::InProgress.as_str())
+        .bind(GameStatus::Paused.as_str())
+        .execute(&data.postgres_pool)
+        .await;
+    if let Err(error) = abandon_result {
+        error_log(format!("database error (load_existing_games_from_database) {}", error));
+    }
+
+    let query = "SELECT admin_uuid, name, uuid, board_size, player_count, show_progress, status, allow_spectators, public, invite_code, rated, seed, move_limit_seconds, time_bank_seconds, increment_seconds, timeout_action, disconnected_seat, team_mode, variant FROM game";
     let rows: Vec<LoadGameFromDb> = sqlx::query_as(query).fetch_all(&data.postgres_pool).await.unwrap();
     let mut games = data.games.write();
+    let players = data.players.read();
 
     rows.iter().for_each(|r| {
         let game_uuid = Uuid::parse_str(r.uuid.as_str()).unwrap();
+        let admin_uuid = Uuid::parse_str(r.admin_uuid.as_str()).unwrap();
+        let variant = GameVariant::parse(&r.variant).unwrap_or_default();
         games.insert(game_uuid, Arc::new(RwLock::new(Game {
             player_count: r.player_count as i8,
             player_to_move: None,
-            admin_uuid: Uuid::parse_str(r.admin_uuid.as_str()).unwrap(),
+            admin_uuid,
             board_size: r.board_size,
-            hex_pairs_in_bag: HexPairsInBag::new(),
+            hex_pairs_in_bag: variant.rules().hex_pairs_in_bag(r.seed as u64),
             name: r.name.clone(),
             show_progress: r.show_progress,
-            status: r.status.clone(),
+            status: r.status.parse().unwrap_or_else(|error| {
+                error_log(error);
+                GameStatus::Abandoned
+            }),
             uuid: game_uuid,
             players: Vec::new(),
-            admin_id: 0,
+            admin_id: players.get(&admin_uuid).map_or(0, |player_rwlock| player_rwlock.read().id),
+            allow_spectators: r.allow_spectators,
+            spectators: Vec::new(),
+            public: r.public,
+            invite_code: r.invite_code.clone(),
+            rated: r.rated,
+            seed: r.seed as u64,
+            move_count: 0,
+            turn_count: 0,
+            time_control: (r.move_limit_seconds.is_some() || r.time_bank_seconds.is_some()).then(|| TimeControl {
+                moveLimitSeconds: r.move_limit_seconds.map(|seconds| seconds as u32),
+                timeBankSeconds: r.time_bank_seconds.map(|seconds| seconds as u32),
+                incrementSeconds: r.increment_seconds as u32,
+                timeoutAction: TimeoutAction::parse(&r.timeout_action).unwrap_or_default(),
+            }),
+            clock: None,
+            forfeited: Vec::new(),
+            passes_in_a_row: 0,
+            disconnected_seat: DisconnectedSeatAction::parse(&r.disconnected_seat).unwrap_or_default(),
+            disconnected_seats: HashMap::new(),
+            team_mode: r.team_mode,
+            teams: HashMap::new(),
+            variant,
+            last_placement: None,
+            undo_accepted_by: None,
         })));
     });
 }
@@ -61,8 +126,11 @@ pub async fn load_existing_players_from_database(data: &web::Data<AppState>) {
         name: String,
         game_uuid: Option<String>,
         id: i32,
+        moderator: bool,
+        rating: f64,
+        bot: Option<String>,
     }
-    let query = "SELECT uuid, name, game_uuid, id FROM player";
+    let query = "SELECT uuid, name, game_uuid, id, moderator, rating, bot FROM player";
     let rows: Result<Vec<LoadPlayerFromDb>, Error> = sqlx::query_as(query).fetch_all(&data.postgres_pool).await;
 
     if let Err(error) = rows {
@@ -83,83 +151,211 @@ pub async fn load_existing_players_from_database(data: &web::Data<AppState>) {
             hex_pairs: [None, None, None, None, None, None],
             moves_in_turn: 0,
             progress: Progress::new(),
+            moderator: r.moderator,
+            rating: r.rating,
+            bot: r.bot.as_deref().and_then(BotDifficulty::parse),
         })));
     });
 }
 
-pub async fn api_game_create(body: web::Json<CreateGameSchema>, data: web::Data<AppState>) -> HttpResponse {
+// creates the game in the database and in memory, shared by the lobby form and matchmaking
+pub async fn create_game(data: &web::Data<AppState>, settings: &CreateGameSchema) -> Result<(Uuid, Option<String>), Error> {
     let uuid = Uuid::new_v4();
-    // let query_result  = sqlx::query(
-    //     r#"INSERT INTO game (uuid, name, board_size, player_count, show_progress, admin_uuid) VALUES ($1, $2, $3, $4, $5, $6)"#,
-    // )
-    //     .bind(uuid)
-    //     .bind(body.name.to_string())
-    //     .bind(body.boardSize.clone())
-    //     .bind(body.playerCount)
-    //     .bind(body.showProgress)
-    //     .bind(body.playerUuid.clone())
-    //     .execute(&data.postgres_pool)
-    //     .await;
+    let allow_spectators = settings.allowSpectators.unwrap_or(true);
+    let public = settings.public.unwrap_or(true);
+    let rated = settings.rated.unwrap_or(false);
+    let invite_code = if public { None } else { Some(generate_invite_code()) };
+    let seed = rand::random::<u64>();
+    let time_control = settings.timeControl;
+    let disconnected_seat = settings.disconnectedSeat.unwrap_or_default();
+    let team_mode = settings.teamMode.unwrap_or(false);
+    let variant = settings.variant.unwrap_or_default();
+    sqlx::query(
+        r#"INSERT INTO game (uuid, name, board_size, player_count, show_progress, admin_uuid, status, allow_spectators, public, invite_code, rated, seed, move_limit_seconds, time_bank_seconds, increment_seconds, timeout_action, disconnected_seat, team_mode, variant)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)"#,
+    )
+        .bind(uuid.to_string())
+        .bind(settings.name.to_string())
+        .bind(settings.boardSize)
+        .bind(settings.playerCount)
+        .bind(settings.showProgress)
+        .bind(settings.playerUuid.to_string())
+        .bind(GameStatus::Created.as_str())
+        .bind(allow_spectators)
+        .bind(public)
+        .bind(&invite_code)
+        .bind(rated)
+        .bind(seed as i64)
+        .bind(time_control.and_then(|time_control| time_control.moveLimitSeconds).map(|seconds| seconds as i32))
+        .bind(time_control.and_then(|time_control| time_control.timeBankSeconds).map(|seconds| seconds as i32))
+        .bind(time_control.map_or(0, |time_control| time_control.incrementSeconds as i32))
+        .bind(time_control.map_or(TimeoutAction::default(), |time_control| time_control.timeoutAction).as_str())
+        .bind(disconnected_seat.as_str())
+        .bind(team_mode)
+        .bind(variant.as_str())
+        .execute(&data.postgres_pool)
+        .await?;
 
     data.boards.write().insert(uuid, Arc::new(RwLock::new(Vec::new())));
 
     data.games.write().insert(uuid, Arc::new(RwLock::new(Game {
-        player_count: body.playerCount as i8,
+        player_count: settings.playerCount as i8,
         player_to_move: None,
-        admin_uuid: body.playerUuid.clone(),
-        board_size: body.boardSize,
-        hex_pairs_in_bag: HexPairsInBag::new(),
-        name: body.name.clone(),
-        show_progress: body.showProgress,
-        status: "created".to_string(),
-        uuid: uuid,
+        admin_uuid: settings.playerUuid,
+        board_size: settings.boardSize,
+        hex_pairs_in_bag: variant.rules().hex_pairs_in_bag(seed),
+        name: settings.name.clone(),
+        show_progress: settings.showProgress,
+        status: GameStatus::Created,
+        uuid,
         players: Vec::new(),
-        admin_id: 0,
+        admin_id: data.players.read().get(&settings.playerUuid).map_or(0, |player_rwlock| player_rwlock.read().id),
+        allow_spectators,
+        spectators: Vec::new(),
+        public,
+        invite_code: invite_code.clone(),
+        rated,
+        seed,
+        move_count: 0,
+        turn_count: 0,
+        time_control,
+        clock: None,
+        forfeited: Vec::new(),
+        passes_in_a_row: 0,
+        disconnected_seat,
+        disconnected_seats: HashMap::new(),
+        team_mode,
+        teams: HashMap::new(),
+        variant,
+        last_placement: None,
+        undo_accepted_by: None,
     })));
 
-    // data.broadcaster.broadcast(json!({ "type": "game_created", "game": {
-    //     "name": body.name,
-    //     "boardSize": body.boardSize,
-    //     "playerCount": body.playerCount,
-    //     "uuid": uuid,
-    //     "showProgress": body.showProgress
-    // }}).to_string().as_str()).await;
+    broadcast_lobby_game_change(data, LobbyGameChange::Created, &uuid);
 
-    HttpResponse::Ok().json(serde_json::json!({ "status": "success" }))
+    Ok((uuid, invite_code))
 }
 
-pub async fn api_get_games(state: web::Data<AppState>) -> HttpResponse {
-    #[derive(Serialize)]
-    struct ApiGetGames {
-        uuid: Uuid,
-        name: String,
-        boardSize: i32,
-        playerCount: i8,
-        showProgress: bool,
-        status: String,
-        players: Vec<ApiLobbyPlayerState>,
+pub async fn api_game_create(body: web::Json<CreateGameSchema>, data: web::Data<AppState>) -> HttpResponse {
+    if let Some(Err(message)) = body.timeControl.map(|time_control| time_control.validate()) {
+        return HttpResponse::BadRequest().json(json!({ "status": "error", "message": message }));
+    }
+    if !(6..=8).contains(&body.boardSize) {
+        return HttpResponse::BadRequest().json(json!({ "status": "error", "message": "board size must be between 6 and 8" }));
+    }
+    // solitaire has its own check below, it is the only variant with a single seat
+    if body.variant != Some(GameVariant::Solitaire) && !(2..=4).contains(&body.playerCount) {
+        return HttpResponse::BadRequest().json(json!({ "status": "error", "message": "player count must be between 2 and 4" }));
+    }
+    if body.teamMode == Some(true) && body.playerCount != 4 {
+        return HttpResponse::BadRequest().json(json!({ "status": "error", "message": "team play needs four players" }));
+    }
+    if body.teamMode == Some(true) && body.rated == Some(true) {
+        return HttpResponse::BadRequest().json(json!({ "status": "error", "message": "team games can not be rated" }));
+    }
+    if body.variant == Some(GameVariant::Solitaire) && (body.playerCount != 1 || body.rated == Some(true) || body.teamMode == Some(true)) {
+        return HttpResponse::BadRequest().json(json!({ "status": "error", "message": "solitaire is played alone and unrated" }));
     }
 
-    let data: Vec<ApiGetGames> = state.games.read().iter().map(|(_, game_lock)| {
-        let game = game_lock.read();
-        let api_game = ApiGetGames {
-            uuid: game.uuid,
-            name: game.name.clone(),
-            boardSize: game.board_size.into(),
-            playerCount: game.player_count,
-            showProgress: game.show_progress,
-            status: game.status.clone(),
-            players: collect_lobby_game_player_state(&state, &game.uuid),
-        };
-        api_game
-    }).collect();
+    match create_game(&data, &body).await {
+        Ok((uuid, invite_code)) => {
+            HttpResponse::Ok().json(serde_json::json!({ "status": "success", "uuid": uuid, "inviteCode": invite_code }))
+        }
+        Err(error) => {
+            error_log(format!("database error (api_game_create) {}", error));
+            HttpResponse::InternalServerError().json(json!({ "status": "error" }))
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ApiGetGames {
+    uuid: Uuid,
+    name: String,
+    boardSize: i32,
+    playerCount: i8,
+    showProgress: bool,
+    status: GameStatus,
+    allowSpectators: bool,
+    rated: bool,
+    teamMode: bool,
+    variant: GameVariant,
+    adminId: i32,
+    players: Vec<ApiLobbyPlayerState>,
+}
+
+fn collect_lobby_game_summary(state: &web::Data<AppState>, game: &Game) -> ApiGetGames {
+    ApiGetGames {
+        uuid: game.uuid,
+        name: game.name.clone(),
+        boardSize: game.board_size,
+        playerCount: game.player_count,
+        showProgress: game.show_progress,
+        status: game.status,
+        allowSpectators: game.allow_spectators,
+        rated: game.rated,
+        teamMode: game.team_mode,
+        variant: game.variant,
+        adminId: game.admin_id,
+        players: collect_lobby_game_player_state(state, &game.uuid),
+    }
+}
+
+pub async fn api_get_games(state: web::Data<AppState>) -> HttpResponse {
+    let data: Vec<ApiGetGames> = state.games.read().iter()
+        .filter(|(_, game_lock)| game_lock.read().public)
+        .map(|(_, game_lock)| collect_lobby_game_summary(&state, &game_lock.read()))
+        .collect();
 
     HttpResponse::Ok().json(json!(data))
 }
 
+pub enum LobbyGameChange {
+    Created,
+    Updated,
+    Removed,
+}
+
+// keeps the game list of every connected client in sync with the Games map
+pub fn broadcast_lobby_game_change(data: &web::Data<AppState>, change: LobbyGameChange, game_uuid: &Uuid) {
+    let Some(game_lock) = data.games.read().get(game_uuid).cloned() else {
+        error_log(format!("game not found while broadcasting lobby game change: {}", game_uuid));
+        return;
+    };
+    // private games are only reachable through their invite code
+    if !game_lock.read().public {
+        return;
+    }
+
+    let payload = match change {
+        LobbyGameChange::Created | LobbyGameChange::Updated => {
+            let game = collect_lobby_game_summary(data, &game_lock.read());
+
+            json!({
+                "type": if let LobbyGameChange::Created = change { "game_created" } else { "game_updated" },
+                "data": {
+                    "game": game,
+                }
+            })
+        }
+        LobbyGameChange::Removed => {
+            json!({
+                "type": "game_removed",
+                "data": {
+                    "uuid": game_uuid,
+                }
+            })
+        }
+    };
+
+    data.rooms_state.read().unwrap().broadcast_to_room(LOBBY_ROOM, payload.to_string().as_str(), None);
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ApiLobbyGameSchema {
     pub gameUuid: Uuid,
+    pub playerUuid: Uuid,
+    pub inviteCode: Option<String>,
 }
 
 pub async fn api_get_lobby_game(body: web::Json<ApiLobbyGameSchema>, state: web::Data<AppState>) -> impl Responder {
@@ -170,11 +366,17 @@ pub async fn api_get_lobby_game(body: web::Json<ApiLobbyGameSchema>, state: web:
         boardSize: i32,
         playerCount: i8,
         showProgress: bool,
-        status: String,
+        status: GameStatus,
+        allowSpectators: bool,
         players: Vec<ApiLobbyPlayerState>,
     }
 
-    if let Some(game_lock) = state.games.read().get(&body.gameUuid) {
+    // a private game is not found for players that can not enter it
+    let game_lock = state.games.read().get(&body.gameUuid)
+        .filter(|game_lock| game_lock.read().can_enter(&body.playerUuid, body.inviteCode.as_deref()))
+        .cloned();
+
+    if let Some(game_lock) = game_lock {
         let game = game_lock.read();
 
         HttpResponse::Ok().json(json!(ApiLobbyGame {
@@ -183,7 +385,8 @@ pub async fn api_get_lobby_game(body: web::Json<ApiLobbyGameSchema>, state: web:
             boardSize: game.board_size.into(),
             playerCount: game.player_count,
             showProgress: game.show_progress,
-            status: game.status.clone(),
+            status: game.status,
+            allowSpectators: game.allow_spectators,
             players: collect_lobby_game_player_state(&state, &body.gameUuid),
         }))
     } else {
@@ -192,17 +395,37 @@ pub async fn api_get_lobby_game(body: web::Json<ApiLobbyGameSchema>, state: web:
     }
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiGameInviteSchema {
+    pub inviteCode: String,
+}
+
+pub async fn api_get_game_by_invite(body: web::Json<ApiGameInviteSchema>, state: web::Data<AppState>) -> HttpResponse {
+    let invite_code = body.inviteCode.trim().to_uppercase();
+    let game_lock = state.games.read().values()
+        .find(|game_lock| game_lock.read().invite_code.as_deref() == Some(invite_code.as_str()))
+        .cloned();
+
+    match game_lock {
+        Some(game_lock) => HttpResponse::Ok().json(json!(collect_lobby_game_summary(&state, &game_lock.read()))),
+        None => HttpResponse::NotFound().json(json!({ "status": "error", "message": "invalid invite code" })),
+    }
+}
+
 #[derive(Serialize)]
 pub struct ApiLobbyPlayerState {
     ready: bool,
     id: i32,
     name: String,
+    bot: Option<BotDifficulty>,
+    team: Option<usize>,
 }
 
 pub fn collect_lobby_game_player_state(data: &web::Data<AppState>, game_uuid: &Uuid) -> Vec<ApiLobbyPlayerState> {
     match data.games.read().get(game_uuid) {
          Some(game) => {
-             game.read().players.iter().fold(Vec::new(), |mut acc, uuid| {
+             let game_read = game.read();
+             game_read.players.iter().fold(Vec::new(), |mut acc, uuid| {
                  match data.players.read().get(&uuid) {
                      Some(player_rwlock) => {
                          let player_read = player_rwlock.read();
@@ -210,6 +433,8 @@ pub fn collect_lobby_game_player_state(data: &web::Data<AppState>, game_uuid: &U
                              ready: player_read.ready,
                              id: player_read.id,
                              name: player_read.name.clone(),
+                             bot: player_read.bot,
+                             team: game_read.team_of(uuid),
                          });
                      }
                      None => {
@@ -226,6 +451,67 @@ pub fn collect_lobby_game_player_state(data: &web::Data<AppState>, game_uuid: &U
     }
 }
 
+pub fn persist_game_status(data: &web::Data<AppState>, game_uuid: &Uuid, status: GameStatus) {
+    let postgres_pool = data.postgres_pool.clone();
+    let game_uuid = game_uuid.to_string();
+
+    // spawned so callers holding game or player locks never wait on the database
+    actix_web::rt::spawn(async move {
+        let query_result = sqlx::query("UPDATE game SET status = $1 WHERE uuid = $2")
+            .bind(status.as_str())
+            .bind(&game_uuid)
+            .execute(&postgres_pool)
+            .await;
+        if let Err(error) = query_result {
+            error_log(format!("database error (persist_game_status) for game {} {}", game_uuid, error));
+        }
+    });
+}
+
+// unseats (or stops spectating) the player and takes them out of the game room
+pub fn remove_player_from_game(data: &web::Data<AppState>, player_uuid: &Uuid, game_uuid: &Uuid) {
+    data.rooms_state.write().unwrap().leave_room(&game_uuid.to_string(), &player_uuid.to_string());
+
+    match data.players.read().get(player_uuid) {
+        Some(player_rwlock) => {
+            let mut player_write = player_rwlock.write();
+            if player_write.game_uuid == Some(*game_uuid) {
+                player_write.game_uuid = None;
+            }
+            player_write.ready = false;
+        }
+        None => {
+            error_log(format!("player not found while leaving the game {}", &player_uuid));
+        }
+    }
+
+    match data.games.read().get(game_uuid) {
+        Some(game_rwlock) => {
+            game_rwlock.write().remove_player(player_uuid);
+        }
+        None => {
+            error_log(format!("game not found while leaving the game {}", &game_uuid));
+        }
+    }
+}
+
+// a player leaving on their own keeps their seat in a running game, the check and the unseating happen under one game lock
+pub fn leave_game(data: &web::Data<AppState>, player_uuid: &Uuid, game_uuid: &Uuid) -> Result<(), &'static str> {
+    match data.games.read().get(game_uuid) {
+        Some(game_rwlock) => {
+            let mut game_write = game_rwlock.write();
+            if !game_write.can_leave(player_uuid) {
+                return Err("a running game can not be left");
+            }
+            game_write.remove_player(player_uuid);
+        }
+        None => return Err("game not found"),
+    }
+
+    remove_player_from_game(data, player_uuid, game_uuid);
+    Ok(())
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PlayerInfo {
     pub playerUuid: Uuid,
@@ -235,10 +521,11 @@ pub async fn api_player_info(body: web::Json<PlayerInfo>, data: web::Data<AppSta
     #[derive(sqlx::FromRow)]
     struct UpsertPlayer {
         id: i32,
+        rating: f64,
     }
 
     let random_name = get_random_name();
-    let upsert_result: Result<UpsertPlayer, Error> = sqlx::query_as(r#"INSERT INTO player (uuid, name) VALUES ($1, $2) ON CONFLICT (uuid) DO UPDATE SET name = $2 RETURNING id, name"#)
+    let upsert_result: Result<UpsertPlayer, Error> = sqlx::query_as(r#"INSERT INTO player (uuid, name) VALUES ($1, $2) ON CONFLICT (uuid) DO UPDATE SET name = $2 RETURNING id, name, rating"#)
         .bind(body.playerUuid.clone())
         .bind(random_name.clone())
         .fetch_one(&data.postgres_pool)
@@ -249,7 +536,7 @@ pub async fn api_player_info(body: web::Json<PlayerInfo>, data: web::Data<AppSta
         return HttpResponse::InternalServerError().json(json!({ "type": "player_info", "status": "error" }));
     }
 
-    let player_id = upsert_result.unwrap().id;
+    let UpsertPlayer { id: player_id, rating } = upsert_result.unwrap();
     let mut game_uuid = None;
     let players_read = data.players.read();
 
@@ -268,6 +555,9 @@ pub async fn api_player_info(body: web::Json<PlayerInfo>, data: web::Data<AppSta
                 hex_pairs: [None, None, None, None, None, None],
                 moves_in_turn: 0,
                 progress: Progress::new(),
+                moderator: false,
+                rating,
+                bot: None,
             })));
         }
     }
@@ -281,6 +571,7 @@ pub async fn api_player_info(body: web::Json<PlayerInfo>, data: web::Data<AppSta
                     "id": player_id,
                     "name": random_name,
                     "gameUuid": game_uuid,
+                    "rating": rating,
                     // "hexPairs": player.hex_pairs,
                     // "movesInTurn": player.moves_in_turn,
                     // "progress": player.progress,
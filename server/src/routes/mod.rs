@@ -1 +1,7 @@
 pub mod lobby;
+pub mod admin;
+pub mod leaderboard;
+pub mod stats;
+pub mod history;
+pub mod analysis;
+pub mod puzzle;
@@ -0,0 +1,188 @@
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::Error;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::game::rules::StandardRules;
+use crate::game::puzzle::{generate_puzzle, score_placements, Puzzle, PuzzlePlacement, MAX_PUZZLE_MOVES, MIN_PUZZLE_MOVES};
+use crate::util::error_log;
+
+const GENERATE_ATTEMPTS: usize = 50;
+const PUZZLE_COLUMNS: &str = "id, seed, board_size, moves, target_points, board::text AS board, hex_pairs::text AS hex_pairs, attempts, solves";
+
+#[derive(sqlx::FromRow)]
+struct PuzzleRow {
+    id: i32,
+    seed: i64,
+    board_size: i32,
+    moves: i32,
+    target_points: i32,
+    board: Option<String>, // json, puzzles stored before the board was kept only have their seed
+    hex_pairs: Option<String>,
+    attempts: i32,
+    solves: i32,
+}
+
+impl PuzzleRow {
+    fn solve_rate(&self) -> f64 {
+        if self.attempts == 0 { 0.0 } else { self.solves as f64 / self.attempts as f64 }
+    }
+
+    fn stored_puzzle(&self) -> Option<Puzzle> {
+        Some(Puzzle {
+            seed: self.seed as u64,
+            board_size: self.board_size,
+            board: serde_json::from_str(self.board.as_deref()?).ok()?,
+            hex_pairs: serde_json::from_str(self.hex_pairs.as_deref()?).ok()?,
+            moves: self.moves as usize,
+            target_points: self.target_points as u32,
+        })
+    }
+
+    fn response(&self, puzzle: &Puzzle) -> serde_json::Value {
+        json!({
+            "status": "success",
+            "puzzleId": self.id,
+            "puzzle": puzzle,
+            "attempts": self.attempts,
+            "solves": self.solves,
+            "solveRate": self.solve_rate(),
+        })
+    }
+}
+
+fn database_error_response(source: &str, error: Error) -> HttpResponse {
+    error_log(format!("database error ({}) {}", source, error));
+    HttpResponse::InternalServerError().json(json!({ "status": "error" }))
+}
+
+async fn load_puzzle(data: &web::Data<AppState>, puzzle_id: i32, source: &str) -> Result<(PuzzleRow, Puzzle), HttpResponse> {
+    let row: Option<PuzzleRow> = sqlx::query_as(&format!("SELECT {} FROM puzzle WHERE id = $1", PUZZLE_COLUMNS))
+        .bind(puzzle_id)
+        .fetch_optional(&data.postgres_pool)
+        .await
+        .map_err(|error| database_error_response(source, error))?;
+    let Some(row) = row else {
+        return Err(HttpResponse::NotFound().json(json!({ "status": "error", "message": "puzzle not found" })));
+    };
+
+    if let Some(puzzle) = row.stored_puzzle() {
+        return Ok((row, puzzle));
+    }
+
+    // older puzzles are generated again from their seed, a different target means the rules changed since
+    match generate_puzzle(&StandardRules, row.seed as u64, row.board_size, row.moves as usize) {
+        Some(puzzle) if puzzle.target_points == row.target_points as u32 => Ok((row, puzzle)),
+        _ => {
+            error_log(format!("puzzle {} can not be generated from its seed as it was stored ({})", puzzle_id, source));
+            Err(HttpResponse::InternalServerError().json(json!({ "status": "error" })))
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiPuzzleCreateSchema {
+    pub boardSize: Option<i32>,
+    pub moves: Option<usize>,
+}
+
+pub async fn api_puzzle_create(body: web::Json<ApiPuzzleCreateSchema>, data: web::Data<AppState>) -> HttpResponse {
+    let board_size = body.boardSize.unwrap_or(6);
+    let moves = body.moves.unwrap_or(2);
+    if !(6..=8).contains(&board_size) {
+        return HttpResponse::BadRequest().json(json!({ "status": "error", "message": "board size must be between 6 and 8" }));
+    }
+    if !(MIN_PUZZLE_MOVES..=MAX_PUZZLE_MOVES).contains(&moves) {
+        return HttpResponse::BadRequest().json(json!({ "status": "error", "message": "a puzzle has 1 to 3 moves" }));
+    }
+
+    let Some(puzzle) = (0..GENERATE_ATTEMPTS).find_map(|_| generate_puzzle(&StandardRules, rand::random::<u64>() >> 1, board_size, moves)) else {
+        error_log(format!("no puzzle found in {} attempts (api_puzzle_create)", GENERATE_ATTEMPTS));
+        return HttpResponse::InternalServerError().json(json!({ "status": "error" }));
+    };
+
+    let row: Result<PuzzleRow, Error> = sqlx::query_as(&format!(r#"
+        INSERT INTO puzzle (seed, board_size, moves, target_points, board, hex_pairs) VALUES ($1, $2, $3, $4, $5::jsonb, $6::jsonb)
+        ON CONFLICT (seed, board_size, moves) DO UPDATE SET target_points = $4, board = $5::jsonb, hex_pairs = $6::jsonb
+        RETURNING {}
+    "#, PUZZLE_COLUMNS))
+        .bind(puzzle.seed as i64)
+        .bind(board_size)
+        .bind(moves as i32)
+        .bind(puzzle.target_points as i32)
+        .bind(json!(puzzle.board).to_string())
+        .bind(json!(puzzle.hex_pairs).to_string())
+        .fetch_one(&data.postgres_pool)
+        .await;
+
+    match row {
+        Ok(row) => HttpResponse::Ok().json(row.response(&puzzle)),
+        Err(error) => database_error_response("api_puzzle_create", error),
+    }
+}
+
+pub async fn api_puzzle_get(path: web::Path<i32>, data: web::Data<AppState>) -> HttpResponse {
+    match load_puzzle(&data, path.into_inner(), "api_puzzle_get").await {
+        Ok((row, puzzle)) => HttpResponse::Ok().json(row.response(&puzzle)),
+        Err(response) => response,
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiPuzzleSolveSchema {
+    pub playerUuid: Uuid,
+    pub placements: Vec<PuzzlePlacement>,
+}
+
+// every valid attempt counts for the solve rate, the player's points are kept with it
+pub async fn api_puzzle_solve(path: web::Path<i32>, body: web::Json<ApiPuzzleSolveSchema>, data: web::Data<AppState>) -> HttpResponse {
+    if !data.players.read().contains_key(&body.playerUuid) {
+        return HttpResponse::NotFound().json(json!({ "status": "error", "message": "player not found" }));
+    }
+
+    let (row, puzzle) = match load_puzzle(&data, path.into_inner(), "api_puzzle_solve").await {
+        Ok(loaded) => loaded,
+        Err(response) => return response,
+    };
+
+    let points = match score_placements(&StandardRules, &puzzle, &body.placements) {
+        Ok(points) => points,
+        Err(message) => return HttpResponse::BadRequest().json(json!({ "status": "error", "message": message })),
+    };
+    let solved = points >= puzzle.target_points;
+
+    let attempt_result = sqlx::query("INSERT INTO puzzle_attempt (puzzle_id, player_uuid, points, solved) VALUES ($1, $2, $3, $4)")
+        .bind(row.id)
+        .bind(body.playerUuid.to_string())
+        .bind(points as i32)
+        .bind(solved)
+        .execute(&data.postgres_pool)
+        .await;
+    if let Err(error) = attempt_result {
+        return database_error_response("api_puzzle_solve", error);
+    }
+
+    let row: Result<PuzzleRow, Error> = sqlx::query_as(&format!(r#"
+        UPDATE puzzle SET attempts = attempts + 1, solves = solves + $2 WHERE id = $1
+        RETURNING {}
+    "#, PUZZLE_COLUMNS))
+        .bind(row.id)
+        .bind(solved as i32)
+        .fetch_one(&data.postgres_pool)
+        .await;
+
+    match row {
+        Ok(row) => HttpResponse::Ok().json(json!({
+            "status": "success",
+            "solved": solved,
+            "points": points,
+            "targetPoints": puzzle.target_points,
+            "attempts": row.attempts,
+            "solves": row.solves,
+            "solveRate": row.solve_rate(),
+        })),
+        Err(error) => database_error_response("api_puzzle_solve", error),
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::Error;
+
+use crate::AppState;
+use crate::game::COLORS;
+use crate::types::{Color, Progress};
+use crate::util::error_log;
+
+const FAVORITE_COLOR_COUNT: usize = 3;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct PlayerStats {
+    pub gamesPlayed: usize,
+    pub wins: usize,
+    pub winRate: f64,
+    pub averageLowestColor: f64,
+    pub ingeniousMoves: i64,
+    pub favoriteColors: Vec<Color>, // most placed colors first
+    pub averageScorePerColor: HashMap<Color, f64>,
+}
+
+// results are (rank, final progress) per finished game, moves are (hex1 color, hex2 color, ingenious count) per placement
+pub fn summarize_player_stats(results: &[(i32, Progress)], moves: &[(i32, i32, i32)]) -> PlayerStats {
+    let games_played = results.len();
+    let wins = results.iter().filter(|(rank, _)| *rank == 1).count();
+    let average = |total: f64| if games_played == 0 { 0.0 } else { total / games_played as f64 };
+
+    let lowest_total: f64 = results.iter()
+        .map(|(_, progress)| progress.sorted_values().first().copied().unwrap_or(0) as f64)
+        .sum();
+
+    let average_score_per_color = COLORS.iter().map(|color| {
+        let total: f64 = results.iter().map(|(_, progress)| *progress.0.get(color).unwrap_or(&0) as f64).sum();
+        (*color, average(total))
+    }).collect();
+
+    let mut placements: HashMap<Color, usize> = HashMap::new();
+    moves.iter().for_each(|(hex1_color, hex2_color, _)| {
+        *placements.entry(*hex1_color as Color).or_default() += 1;
+        *placements.entry(*hex2_color as Color).or_default() += 1;
+    });
+    let mut favorite_colors: Vec<(Color, usize)> = placements.into_iter().collect();
+    favorite_colors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    PlayerStats {
+        gamesPlayed: games_played,
+        wins,
+        winRate: average(wins as f64),
+        averageLowestColor: average(lowest_total),
+        ingeniousMoves: moves.iter().map(|(_, _, ingenious_count)| *ingenious_count as i64).sum(),
+        favoriteColors: favorite_colors.into_iter().take(FAVORITE_COLOR_COUNT).map(|(color, _)| color).collect(),
+        averageScorePerColor: average_score_per_color,
+    }
+}
+
+pub async fn api_player_stats(path: web::Path<i32>, data: web::Data<AppState>) -> HttpResponse {
+    let player_id = path.into_inner();
+
+    let player = data.players.read().values()
+        .find(|player_rwlock| player_rwlock.read().id == player_id)
+        .map(|player_rwlock| {
+            let player_read = player_rwlock.read();
+            (player_read.uuid, player_read.name.clone(), player_read.rating)
+        });
+    let Some((player_uuid, name, rating)) = player else {
+        return HttpResponse::NotFound().json(json!({ "status": "error", "message": "player not found" }));
+    };
+
+    let results: Result<Vec<(i32, String)>, Error> = sqlx::query_as("SELECT rank, progress::text FROM game_result WHERE player_uuid = $1")
+        .bind(player_uuid.to_string())
+        .fetch_all(&data.postgres_pool)
+        .await;
+    let moves: Result<Vec<(i32, i32, i32)>, Error> = sqlx::query_as("SELECT hex1_color, hex2_color, ingenious_count FROM game_move WHERE player_uuid = $1")
+        .bind(player_uuid.to_string())
+        .fetch_all(&data.postgres_pool)
+        .await;
+
+    let (results, moves) = match (results, moves) {
+        (Ok(results), Ok(moves)) => (results, moves),
+        (Err(error), _) | (_, Err(error)) => {
+            error_log(format!("database error (api_player_stats) {}", error));
+            return HttpResponse::InternalServerError().json(json!({ "status": "error" }));
+        }
+    };
+
+    let results: Vec<(i32, Progress)> = results.into_iter()
+        .filter_map(|(rank, progress)| serde_json::from_str(&progress).ok().map(|progress| (rank, progress)))
+        .collect();
+
+    HttpResponse::Ok().json(json!({
+        "status": "success",
+        "data": {
+            "id": player_id,
+            "name": name,
+            "rating": rating,
+            "stats": summarize_player_stats(&results, &moves),
+        }
+    }))
+}
+
+#[test]
+fn test_summarize_player_stats() {
+    let mut progress = Progress::new();
+    COLORS.iter().for_each(|color| { progress.0.insert(*color, 10); });
+    progress.0.insert(2, 4);
+
+    let stats = summarize_player_stats(&[(1, progress.clone()), (2, Progress::new())], &[(2, 2, 0), (2, 5, 1), (5, 0, 1)]);
+
+    assert_eq!(stats.gamesPlayed, 2);
+    assert_eq!(stats.winRate, 0.5);
+    assert_eq!(stats.averageLowestColor, 2.0);
+    assert_eq!(stats.ingeniousMoves, 2);
+    assert_eq!(stats.favoriteColors, vec![2, 5, 0]);
+    assert_eq!(stats.averageScorePerColor[&1], 5.0);
+}
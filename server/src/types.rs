@@ -1,10 +1,19 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
 use std::sync::{Arc};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use serde::Serializer;
+use std::time::Instant;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
 use crate::game::HexPairsInBag;
+use crate::game::bot::BotDifficulty;
+use crate::game::clock::{GameClock, TimeControl};
+use crate::game::rules::{RuleSet, SolitaireRules, StandardRules};
+use crate::game::undo::PlacementSnapshot;
 use crate::util::error_log;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
@@ -35,6 +44,9 @@ pub struct Player {
     pub hex_pairs: HexPairs,
     pub moves_in_turn: i8,
     pub progress: Progress,
+    pub moderator: bool,
+    pub rating: f64,
+    pub bot: Option<BotDifficulty>, // computer players take their turns on the server
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -66,6 +78,13 @@ impl Progress {
         }
     }
 
+    // scores ordered from the lowest color up, Ingenious compares players by these
+    pub fn sorted_values(&self) -> Vec<u8> {
+        let mut values: Vec<u8> = self.0.values().copied().collect();
+        values.sort();
+        values
+    }
+
     pub fn sum(self, progress: Progress) -> Progress {
         self.0.iter().fold(Progress::new(), |mut acc: Progress, (color, value)| {
             match progress.0.get(color) {
@@ -81,6 +100,12 @@ impl Progress {
     }
 }
 
+impl Default for Progress {
+    fn default() -> Self {
+        Progress::new()
+    }
+}
+
 #[derive(PartialEq, Clone)]
 pub struct Point {
     pub x: i8,
@@ -93,6 +118,125 @@ pub type HexPair = [Color; 2];
 
 pub type HexPairs = [Option<HexPair>; 6];
 
+// what happens on the turns of a player that stayed disconnected past the grace period
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DisconnectedSeatAction {
+    #[default]
+    Skip,
+    Bot,
+}
+
+impl DisconnectedSeatAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DisconnectedSeatAction::Skip => "skip",
+            DisconnectedSeatAction::Bot => "bot",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<DisconnectedSeatAction> {
+        [DisconnectedSeatAction::Skip, DisconnectedSeatAction::Bot].into_iter().find(|action| action.as_str() == s)
+    }
+}
+
+// which rules the game is played by, they decide the scoring and when the game ends
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GameVariant {
+    #[default]
+    Standard,
+    Solitaire,
+}
+
+impl GameVariant {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GameVariant::Standard => "standard",
+            GameVariant::Solitaire => "solitaire",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<GameVariant> {
+        [GameVariant::Standard, GameVariant::Solitaire].into_iter().find(|variant| variant.as_str() == s)
+    }
+
+    pub fn rules(&self) -> &'static dyn RuleSet {
+        match self {
+            GameVariant::Standard => &StandardRules,
+            GameVariant::Solitaire => &SolitaireRules,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DisconnectedSeat {
+    pub since: Instant,
+    pub grace_period_over: bool, // from then on the seat is played automatically and can be voted out
+    pub removal_votes: HashSet<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Copy, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum GameStatus {
+    Created,
+    Starting,
+    InProgress,
+    Paused,
+    Ended,
+    Abandoned,
+}
+
+impl GameStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GameStatus::Created => "created",
+            GameStatus::Starting => "starting",
+            GameStatus::InProgress => "in_progress",
+            GameStatus::Paused => "paused",
+            GameStatus::Ended => "ended",
+            GameStatus::Abandoned => "abandoned",
+        }
+    }
+
+    // started and not over yet, a seated player is bound to the game
+    pub fn is_running(&self) -> bool {
+        matches!(self, GameStatus::Starting | GameStatus::InProgress | GameStatus::Paused)
+    }
+
+    pub fn can_transition_to(&self, next: GameStatus) -> bool {
+        matches!((self, next),
+            (GameStatus::Created, GameStatus::Starting)
+            | (GameStatus::Created, GameStatus::Abandoned)
+            | (GameStatus::Starting, GameStatus::InProgress)
+            | (GameStatus::Starting, GameStatus::Created)
+            | (GameStatus::Starting, GameStatus::Abandoned)
+            | (GameStatus::InProgress, GameStatus::Paused)
+            | (GameStatus::InProgress, GameStatus::Ended)
+            | (GameStatus::InProgress, GameStatus::Abandoned)
+            | (GameStatus::Paused, GameStatus::InProgress)
+            | (GameStatus::Paused, GameStatus::Ended)
+            | (GameStatus::Paused, GameStatus::Abandoned)
+        )
+    }
+}
+
+impl FromStr for GameStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "created" => Ok(GameStatus::Created),
+            "starting" => Ok(GameStatus::Starting),
+            "in_progress" => Ok(GameStatus::InProgress),
+            "paused" => Ok(GameStatus::Paused),
+            "ended" => Ok(GameStatus::Ended),
+            "abandoned" => Ok(GameStatus::Abandoned),
+            _ => Err(format!("unknown game status: {}", s)),
+        }
+    }
+}
+
 pub struct Game {
     pub admin_uuid: Uuid,
     pub admin_id: i32,
@@ -101,10 +245,173 @@ pub struct Game {
     pub player_to_move: Option<Uuid>,
     pub name: String,
     pub show_progress: bool,
-    pub status: String, // "created", "in_progress", "ended"
+    pub status: GameStatus,
     pub uuid: Uuid,
     pub player_count: i8,
     pub players: Vec<Uuid>, // ordered by move sequence
+    pub allow_spectators: bool,
+    pub spectators: Vec<Uuid>,
+    pub public: bool,
+    pub invite_code: Option<String>, // only private games can be joined with an invite code
+    pub rated: bool,
+    pub seed: u64, // seeds the hex pair bag and the seat order
+    pub move_count: u32, // placements made so far, orders persisted moves and draws
+    pub turn_count: u32, // turns ended so far, a turn can have several placements or none
+    pub time_control: Option<TimeControl>,
+    pub clock: Option<GameClock>, // started together with the game
+    pub forfeited: Vec<Uuid>, // seats that are skipped for the rest of the game
+    pub passes_in_a_row: usize, // turns that ended without a placement, everybody passing ends the game
+    pub disconnected_seat: DisconnectedSeatAction,
+    pub disconnected_seats: HashMap<Uuid, DisconnectedSeat>,
+    pub team_mode: bool, // four players in two partnerships sharing a score
+    pub teams: HashMap<Uuid, usize>, // the team every seated player chose in the lobby, only used in team play
+    pub variant: GameVariant,
+    pub last_placement: Option<PlacementSnapshot>, // can be taken back until the next player acts
+    pub undo_accepted_by: Option<HashSet<Uuid>>, // opponents that accepted the pending undo request
+}
+
+impl Game {
+    pub fn can_enter(&self, player_uuid: &Uuid, invite_code: Option<&str>) -> bool {
+        self.public
+            || self.players.contains(player_uuid)
+            || self.admin_uuid == *player_uuid
+            || self.invite_code.as_deref().zip(invite_code).is_some_and(|(code, given)| code.eq_ignore_ascii_case(given.trim()))
+    }
+
+    // spectators come and go, a seated player can only leave before the game starts or once it is over
+    pub fn can_leave(&self, player_uuid: &Uuid) -> bool {
+        !self.players.contains(player_uuid) || !self.status.is_running()
+    }
+
+    pub fn remove_player(&mut self, player_uuid: &Uuid) {
+        self.players.retain(|uuid| uuid != player_uuid);
+        self.teams.remove(player_uuid);
+        self.spectators.retain(|uuid| uuid != player_uuid);
+    }
+
+    pub fn transition_to(&mut self, next: GameStatus) -> Result<(), String> {
+        if !self.status.can_transition_to(next) {
+            return Err(format!("game {} can not go from {} to {}", self.uuid, self.status.as_str(), next.as_str()));
+        }
+        self.status = next;
+        Ok(())
+    }
+
+    pub fn team_of(&self, player_uuid: &Uuid) -> Option<usize> {
+        if !self.team_mode {
+            return None;
+        }
+        self.teams.get(player_uuid).copied()
+    }
+
+    pub fn team_size(&self, team: usize) -> usize {
+        self.teams.values().filter(|other| **other == team).count()
+    }
+
+    // a player taking a seat starts out in the team with fewer players
+    pub fn assign_team(&mut self, player_uuid: &Uuid) {
+        if !self.team_mode || self.teams.contains_key(player_uuid) {
+            return;
+        }
+        let team = (0..TEAM_COUNT).min_by_key(|team| self.team_size(*team)).unwrap_or(0);
+        self.teams.insert(*player_uuid, team);
+    }
+
+    pub fn has_full_teams(&self) -> bool {
+        !self.team_mode || (0..TEAM_COUNT).all(|team| self.team_size(team) == self.player_count as usize / TEAM_COUNT)
+    }
+
+    // the seats are shuffled with the game seed, in team play partners end up across from each other
+    pub fn shuffle_seats(&mut self) {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        if self.team_mode {
+            self.players = interleave_teams(&self.players, &self.teams, &mut rng);
+        } else {
+            self.players.shuffle(&mut rng);
+        }
+    }
+
+    // the team of every seat in seat order, None for every seat when not playing in teams
+    pub fn teams(&self) -> Vec<Option<usize>> {
+        self.players.iter().map(|uuid| self.team_of(uuid)).collect()
+    }
+
+    pub fn teammates(&self, player_uuid: &Uuid) -> Vec<Uuid> {
+        let Some(team) = self.team_of(player_uuid) else {
+            return Vec::new();
+        };
+        self.players.iter().filter(|uuid| *uuid != player_uuid && self.team_of(uuid) == Some(team)).copied().collect()
+    }
+
+    // with show_progress off a seated player only sees their own (and their team's) scores until the game is over,
+    // spectators always see everything
+    pub fn can_see_progress(&self, viewer_uuid: &Uuid, player_uuid: &Uuid) -> bool {
+        self.show_progress
+            || matches!(self.status, GameStatus::Ended | GameStatus::Abandoned)
+            || !self.players.contains(viewer_uuid)
+            || viewer_uuid == player_uuid
+            || self.teammates(viewer_uuid).contains(player_uuid)
+    }
+
+    // hands the move to the next seat that has not forfeited and switches the clock over
+    pub fn end_turn(&mut self, now: Instant) {
+        let Some(current) = self.player_to_move else {
+            return;
+        };
+        if let Some(clock) = &mut self.clock {
+            clock.end_turn(&current, now);
+            clock.start_turn(now);
+        }
+        self.turn_count += 1;
+
+        let seat = self.players.iter().position(|uuid| *uuid == current).unwrap_or(0);
+        self.player_to_move = (1..=self.players.len())
+            .map(|offset| self.players[(seat + offset) % self.players.len()])
+            .find(|uuid| !self.forfeited.contains(uuid));
+    }
+}
+
+pub const TEAM_COUNT: usize = 2;
+
+// shuffles every team on its own and seats the teams in turns, the team that moves first is drawn as well
+fn interleave_teams(players: &[Uuid], teams: &HashMap<Uuid, usize>, rng: &mut StdRng) -> Vec<Uuid> {
+    let mut team_seats: Vec<Vec<Uuid>> = (0..TEAM_COUNT)
+        .map(|team| players.iter().filter(|uuid| teams.get(uuid) == Some(&team)).copied().collect())
+        .collect();
+    team_seats.iter_mut().for_each(|seats| seats.shuffle(rng));
+    team_seats.shuffle(rng);
+
+    let rounds = team_seats.iter().map(Vec::len).max().unwrap_or(0);
+    (0..rounds).flat_map(|round| team_seats.iter().filter_map(move |seats| seats.get(round).copied()).collect::<Vec<Uuid>>()).collect()
 }
 
 pub type Boards = Arc<RwLock<HashMap<Uuid, Arc<RwLock<Vec<BoardHex>>>>>>;
+
+#[test]
+fn test_game_status_transitions() {
+    assert!(GameStatus::Created.can_transition_to(GameStatus::Starting));
+    assert!(GameStatus::InProgress.can_transition_to(GameStatus::Paused));
+    assert!(GameStatus::Paused.can_transition_to(GameStatus::InProgress));
+    assert!(!GameStatus::Created.can_transition_to(GameStatus::InProgress));
+    assert!(!GameStatus::Ended.can_transition_to(GameStatus::InProgress));
+    assert!(!GameStatus::Abandoned.can_transition_to(GameStatus::Created));
+    assert_eq!("in_progress".parse::<GameStatus>(), Ok(GameStatus::InProgress));
+    assert_eq!(serde_json::to_string(&GameStatus::InProgress).unwrap(), format!("\"{}\"", GameStatus::InProgress.as_str()));
+    assert!(GameStatus::Paused.is_running());
+    assert!(!GameStatus::Created.is_running());
+    assert!(!GameStatus::Ended.is_running());
+}
+
+#[test]
+fn test_partners_sit_across_from_each_other() {
+    let players: Vec<Uuid> = (0..4).map(|_| Uuid::new_v4()).collect();
+    let teams: HashMap<Uuid, usize> = players.iter().enumerate().map(|(index, uuid)| (*uuid, index / 2)).collect();
+
+    for seed in 0..10 {
+        let seats = interleave_teams(&players, &teams, &mut StdRng::seed_from_u64(seed));
+        assert_eq!(seats.len(), 4);
+        assert_eq!(teams[&seats[0]], teams[&seats[2]]);
+        assert_eq!(teams[&seats[1]], teams[&seats[3]]);
+        assert_ne!(teams[&seats[0]], teams[&seats[1]]);
+    }
+}
@@ -11,6 +11,15 @@ pub fn error_log(s: String) {
     }
 }
 
+const INVITE_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const INVITE_CODE_LENGTH: usize = 8;
+
+pub fn generate_invite_code() -> String {
+    (0..INVITE_CODE_LENGTH)
+        .map(|_| INVITE_CODE_ALPHABET[(rand::random::<f32>() * INVITE_CODE_ALPHABET.len() as f32).floor() as usize] as char)
+        .collect()
+}
+
 pub fn get_random_name() -> String {
     String::from(NAMES[(rand::random::<f32>() * NAMES.len() as f32).floor() as usize])
 }
@@ -1,6 +1,13 @@
-mod lobby;
+pub mod lobby;
 pub mod game;
 pub mod rooms_state;
+pub mod chat;
+pub mod admin;
+pub mod matchmaking;
+pub mod disconnect;
+pub mod view;
+pub mod undo;
+pub mod game_writes;
 
 use actix_web::{web, Error, HttpRequest, HttpResponse, Responder};
 use actix_web::web::Data;
@@ -12,10 +19,17 @@ use uuid::Uuid;
 use actix_ws::AggregatedMessage;
 use futures_util::StreamExt as _;
 use serde::Deserialize;
+use serde_json::json;
 use crate::AppState;
-use crate::ws::game::{ws_place_hex_pair, WsPlaceHexPair};
-use crate::ws::lobby::{ws_join_game, ws_leave_game, ws_ready_change, WsJoinGame, WsLeaveGame, WsReadyChange};
-use crate::ws::rooms_state::RoomsState;
+use crate::ws::game::{ws_place_hex_pair, ws_request_hint, WsPlaceHexPair, WsRequestHint};
+use crate::ws::lobby::{ws_choose_team, ws_join_game, ws_leave_game, ws_ready_change, ws_spectate_game, WsChooseTeam, WsJoinGame, WsLeaveGame, WsReadyChange, WsSpectateGame};
+use crate::ws::rooms_state::{PresencePlayer, RoomsState, LOBBY_ROOM};
+use crate::util::error_log;
+use crate::ws::chat::{send_chat_history, ws_chat_delete, ws_chat_message, WsChatDelete, WsChatMessage};
+use crate::ws::matchmaking::{ws_matchmaking_dequeue, ws_matchmaking_enqueue, WsMatchmakingDequeue, WsMatchmakingEnqueue};
+use crate::ws::undo::{ws_undo_request, ws_undo_response, WsUndoRequest, WsUndoResponse};
+use crate::ws::disconnect::{mark_player_disconnected, mark_player_reconnected, ws_vote_remove_player, WsVoteRemovePlayer};
+use crate::ws::admin::{ws_add_bot, ws_cancel_game, ws_kick_player, ws_revoke_invite_code, ws_transfer_admin, ws_update_game_settings, WsAddBot, WsCancelGame, WsKickPlayer, WsRevokeInviteCode, WsTransferAdmin, WsUpdateGameSettings};
 
 const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
 const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
@@ -31,7 +45,24 @@ pub async fn websocket_handler(
             let (response, mut session, msg_stream) = actix_ws::handle(&req, stream)?;
             let (client_tx, mut client_rx) = mpsc::unbounded_channel::<String>();
 
-            data.rooms_state.write().unwrap().register_client(&player_uuid.to_string(), client_tx.clone());
+            let presence_player = match data.players.read().get(&player_uuid) {
+                Some(player_rwlock) => {
+                    let player_read = player_rwlock.read();
+                    PresencePlayer { id: player_read.id, name: player_read.name.clone() }
+                }
+                None => {
+                    error_log(format!("player not found while registering ws client {}", player_uuid));
+                    PresencePlayer::default()
+                }
+            };
+
+            let rejoined_rooms = data.rooms_state.write().unwrap().register_client(&player_uuid.to_string(), client_tx.clone(), presence_player);
+            if !rejoined_rooms.iter().any(|room_name| room_name == LOBBY_ROOM) {
+                data.rooms_state.write().unwrap().join_room(LOBBY_ROOM, &player_uuid.to_string());
+                send_chat_history(&data, LOBBY_ROOM, &player_uuid);
+            }
+            rejoined_rooms.iter().for_each(|room_name| send_chat_history(&data, room_name, &player_uuid));
+            mark_player_reconnected(&data, &player_uuid);
 
             // Spawn task to handle the WebSocket
             actix_web::rt::spawn(async move {
@@ -55,10 +86,56 @@ pub enum WsMessage {
     JoinGame(WsJoinGame),
     #[serde(alias="leave_game")]
     LeaveGame(WsLeaveGame),
+    #[serde(alias="spectate_game")]
+    SpectateGame(WsSpectateGame),
     #[serde(alias="ready_change")]
     ReadyChange(WsReadyChange),
+    #[serde(alias="choose_team")]
+    ChooseTeam(WsChooseTeam),
     #[serde(alias="place_hex_pair")]
     PlaceHexPair(WsPlaceHexPair),
+    #[serde(alias="chat_message")]
+    ChatMessage(WsChatMessage),
+    #[serde(alias="chat_delete")]
+    ChatDelete(WsChatDelete),
+    #[serde(alias="kick_player")]
+    KickPlayer(WsKickPlayer),
+    #[serde(alias="transfer_admin")]
+    TransferAdmin(WsTransferAdmin),
+    #[serde(alias="update_game_settings")]
+    UpdateGameSettings(WsUpdateGameSettings),
+    #[serde(alias="cancel_game")]
+    CancelGame(WsCancelGame),
+    #[serde(alias="revoke_invite_code")]
+    RevokeInviteCode(WsRevokeInviteCode),
+    #[serde(alias="add_bot")]
+    AddBot(WsAddBot),
+    #[serde(alias="matchmaking_enqueue")]
+    MatchmakingEnqueue(WsMatchmakingEnqueue),
+    #[serde(alias="matchmaking_dequeue")]
+    MatchmakingDequeue(WsMatchmakingDequeue),
+    #[serde(alias="vote_remove_player")]
+    VoteRemovePlayer(WsVoteRemovePlayer),
+    #[serde(alias="request_hint")]
+    RequestHint(WsRequestHint),
+    #[serde(alias="undo_request")]
+    UndoRequest(WsUndoRequest),
+    #[serde(alias="undo_response")]
+    UndoResponse(WsUndoResponse),
+}
+
+pub fn ws_error_payload(source: &str, message: &str) -> String {
+    json!({
+        "type": "error",
+        "data": {
+            "source": source,
+            "message": message,
+        }
+    }).to_string()
+}
+
+pub fn broadcast_to_game_room(data: &Data<AppState>, game_uuid: &Uuid, payload: serde_json::Value) {
+    data.rooms_state.read().unwrap().broadcast_to_room(&game_uuid.to_string(), payload.to_string().as_str(), None);
 }
 
 async fn handle_websocket_connection(
@@ -120,14 +197,78 @@ async fn handle_websocket_connection(
                                                 println!("{:?}", leave_game_payload);
                                                 ws_leave_game(&app_state, session, &leave_game_payload).await;
                                             }
+                                            WsMessage::SpectateGame(spectate_game_payload) => {
+                                                println!("{:?}", spectate_game_payload);
+                                                ws_spectate_game(&app_state, session, &spectate_game_payload).await;
+                                            }
                                             WsMessage::ReadyChange(check_ready_payload) => {
                                                 println!("{:?}", check_ready_payload);
                                                 ws_ready_change(&app_state, &check_ready_payload).await;
                                             }
+                                            WsMessage::ChooseTeam(choose_team_payload) => {
+                                                println!("{:?}", choose_team_payload);
+                                                ws_choose_team(&app_state, &choose_team_payload).await;
+                                            }
                                             WsMessage::PlaceHexPair(place_hex_pair_payload) => {
                                                 println!("{:?}", place_hex_pair_payload);
                                                 ws_place_hex_pair(&app_state, &place_hex_pair_payload).await;
                                             }
+                                            WsMessage::ChatMessage(chat_message_payload) => {
+                                                println!("{:?}", chat_message_payload);
+                                                ws_chat_message(&app_state, &chat_message_payload).await;
+                                            }
+                                            WsMessage::ChatDelete(chat_delete_payload) => {
+                                                println!("{:?}", chat_delete_payload);
+                                                ws_chat_delete(&app_state, &chat_delete_payload).await;
+                                            }
+                                            WsMessage::KickPlayer(kick_player_payload) => {
+                                                println!("{:?}", kick_player_payload);
+                                                ws_kick_player(&app_state, &kick_player_payload).await;
+                                            }
+                                            WsMessage::TransferAdmin(transfer_admin_payload) => {
+                                                println!("{:?}", transfer_admin_payload);
+                                                ws_transfer_admin(&app_state, &transfer_admin_payload).await;
+                                            }
+                                            WsMessage::UpdateGameSettings(update_game_settings_payload) => {
+                                                println!("{:?}", update_game_settings_payload);
+                                                ws_update_game_settings(&app_state, &update_game_settings_payload).await;
+                                            }
+                                            WsMessage::CancelGame(cancel_game_payload) => {
+                                                println!("{:?}", cancel_game_payload);
+                                                ws_cancel_game(&app_state, &cancel_game_payload).await;
+                                            }
+                                            WsMessage::RevokeInviteCode(revoke_invite_code_payload) => {
+                                                println!("{:?}", revoke_invite_code_payload);
+                                                ws_revoke_invite_code(&app_state, &revoke_invite_code_payload).await;
+                                            }
+                                            WsMessage::AddBot(add_bot_payload) => {
+                                                println!("{:?}", add_bot_payload);
+                                                ws_add_bot(&app_state, &add_bot_payload).await;
+                                            }
+                                            WsMessage::MatchmakingEnqueue(matchmaking_enqueue_payload) => {
+                                                println!("{:?}", matchmaking_enqueue_payload);
+                                                ws_matchmaking_enqueue(&app_state, &matchmaking_enqueue_payload).await;
+                                            }
+                                            WsMessage::MatchmakingDequeue(matchmaking_dequeue_payload) => {
+                                                println!("{:?}", matchmaking_dequeue_payload);
+                                                ws_matchmaking_dequeue(&app_state, &matchmaking_dequeue_payload).await;
+                                            }
+                                            WsMessage::VoteRemovePlayer(vote_remove_player_payload) => {
+                                                println!("{:?}", vote_remove_player_payload);
+                                                ws_vote_remove_player(&app_state, &vote_remove_player_payload).await;
+                                            }
+                                            WsMessage::RequestHint(request_hint_payload) => {
+                                                println!("{:?}", request_hint_payload);
+                                                ws_request_hint(&app_state, &request_hint_payload).await;
+                                            }
+                                            WsMessage::UndoRequest(undo_request_payload) => {
+                                                println!("{:?}", undo_request_payload);
+                                                ws_undo_request(&app_state, &undo_request_payload).await;
+                                            }
+                                            WsMessage::UndoResponse(undo_response_payload) => {
+                                                println!("{:?}", undo_response_payload);
+                                                ws_undo_response(&app_state, &undo_response_payload).await;
+                                            }
                                         }
                                     },
                                     Err(e) =>/**/ println!("Could not parse status: {}\n", e)
@@ -179,6 +320,10 @@ async fn handle_websocket_connection(
     // Client disconnected - clean up
     println!("WebSocket connection closing: {}", client_id);
     app_state.rooms_state.write().unwrap().remove_client(&client_id);
+    if let Ok(player_uuid) = Uuid::parse_str(&client_id) {
+        app_state.matchmaking.write().dequeue(&player_uuid);
+        mark_player_disconnected(&app_state, &player_uuid);
+    }
 }
 
 async fn leave_room(client_id: &str, room_name: &str, rooms_state: &Arc<Mutex<RoomsState>>) {
@@ -205,18 +350,23 @@ pub async fn cleanup_stale_clients(rooms_state: Data<Arc<RwLock<RoomsState>>>) -
     }))
 }
 
-pub async fn start_cleanup_task(rooms_state: Arc<RwLock<RoomsState>>) {
+pub async fn start_cleanup_task(data: Data<AppState>) {
     let cleanup_interval = Duration::from_secs(30); // Run cleanup every 30 seconds
 
     loop {
         sleep(cleanup_interval).await;
 
-        if let Ok(mut state) = rooms_state.write() {
-            let removed = state.remove_stale_clients(CLIENT_TIMEOUT);
+        let removed = match data.rooms_state.write() {
+            Ok(mut state) => state.remove_stale_clients(CLIENT_TIMEOUT),
+            Err(_) => Vec::new(),
+        };
 
-            if !removed.is_empty() {
-                println!("Cleanup task removed {} stale clients", removed.len());
-            }
+        if !removed.is_empty() {
+            println!("Cleanup task removed {} stale clients", removed.len());
         }
+        removed.iter().filter_map(|client_id| Uuid::parse_str(client_id).ok()).for_each(|player_uuid| {
+            data.matchmaking.write().dequeue(&player_uuid);
+            mark_player_disconnected(&data, &player_uuid);
+        });
     }
 }
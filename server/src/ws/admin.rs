@@ -0,0 +1,98 @@
+use actix_web::web::Data;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+use crate::AppState;
+use crate::game::bot::BotDifficulty;
+use crate::routes::admin::{add_bot, cancel_game, kick_player, regenerate_invite_code, transfer_admin, update_game_settings, AdminActionError, GameSettingsChange};
+use crate::ws::ws_error_payload;
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WsKickPlayer {
+    pub player_uuid: Uuid,
+    pub game_uuid: Uuid,
+    pub target_player_id: i32,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WsTransferAdmin {
+    pub player_uuid: Uuid,
+    pub game_uuid: Uuid,
+    pub target_player_id: i32,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WsUpdateGameSettings {
+    pub player_uuid: Uuid,
+    pub game_uuid: Uuid,
+    #[serde(flatten)]
+    pub settings: GameSettingsChange,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WsCancelGame {
+    pub player_uuid: Uuid,
+    pub game_uuid: Uuid,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WsRevokeInviteCode {
+    pub player_uuid: Uuid,
+    pub game_uuid: Uuid,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WsAddBot {
+    pub player_uuid: Uuid,
+    pub game_uuid: Uuid,
+    #[serde(default)]
+    pub difficulty: BotDifficulty,
+}
+
+fn send_admin_action_error(data: &Data<AppState>, player_uuid: &Uuid, source: &str, result: Result<(), AdminActionError>) {
+    if let Err(error) = result {
+        data.rooms_state.read().unwrap().send_to_client(&player_uuid.to_string(), &ws_error_payload(source, error.message()));
+    }
+}
+
+pub async fn ws_kick_player(data: &Data<AppState>, kick_player_payload: &WsKickPlayer) {
+    let result = kick_player(data, &kick_player_payload.player_uuid, &kick_player_payload.game_uuid, kick_player_payload.target_player_id).await;
+    send_admin_action_error(data, &kick_player_payload.player_uuid, "kick_player", result);
+}
+
+pub async fn ws_transfer_admin(data: &Data<AppState>, transfer_admin_payload: &WsTransferAdmin) {
+    let result = transfer_admin(data, &transfer_admin_payload.player_uuid, &transfer_admin_payload.game_uuid, transfer_admin_payload.target_player_id).await;
+    send_admin_action_error(data, &transfer_admin_payload.player_uuid, "transfer_admin", result);
+}
+
+pub async fn ws_update_game_settings(data: &Data<AppState>, update_game_settings_payload: &WsUpdateGameSettings) {
+    let result = update_game_settings(data, &update_game_settings_payload.player_uuid, &update_game_settings_payload.game_uuid, &update_game_settings_payload.settings).await;
+    send_admin_action_error(data, &update_game_settings_payload.player_uuid, "update_game_settings", result);
+}
+
+pub async fn ws_cancel_game(data: &Data<AppState>, cancel_game_payload: &WsCancelGame) {
+    let result = cancel_game(data, &cancel_game_payload.player_uuid, &cancel_game_payload.game_uuid).await;
+    send_admin_action_error(data, &cancel_game_payload.player_uuid, "cancel_game", result);
+}
+
+pub async fn ws_revoke_invite_code(data: &Data<AppState>, revoke_invite_code_payload: &WsRevokeInviteCode) {
+    let player_uuid = &revoke_invite_code_payload.player_uuid;
+
+    match regenerate_invite_code(data, player_uuid, &revoke_invite_code_payload.game_uuid).await {
+        Ok(invite_code) => {
+            data.rooms_state.read().unwrap().send_to_client(&player_uuid.to_string(), json!({
+                "type": "invite_code",
+                "data": {
+                    "game_uuid": revoke_invite_code_payload.game_uuid,
+                    "invite_code": invite_code,
+                }
+            }).to_string().as_str());
+        }
+        Err(error) => send_admin_action_error(data, player_uuid, "revoke_invite_code", Err(error)),
+    }
+}
+
+pub async fn ws_add_bot(data: &Data<AppState>, add_bot_payload: &WsAddBot) {
+    let result = add_bot(data, &add_bot_payload.player_uuid, &add_bot_payload.game_uuid, add_bot_payload.difficulty).await;
+    send_admin_action_error(data, &add_bot_payload.player_uuid, "add_bot", result.map(|_| ()));
+}
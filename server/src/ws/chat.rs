@@ -0,0 +1,229 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use actix_web::web::Data;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+use crate::AppState;
+use crate::util::error_log;
+use crate::ws::rooms_state::LOBBY_ROOM;
+use crate::ws::ws_error_payload;
+
+const CHAT_MESSAGE_MAX_LENGTH: usize = 500;
+const CHAT_HISTORY_LENGTH: usize = 50;
+const CHAT_RATE_LIMIT_COUNT: usize = 5;
+const CHAT_RATE_LIMIT_WINDOW: Duration = Duration::from_secs(10);
+
+pub type Chat = Arc<RwLock<ChatState>>;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ChatMessage {
+    pub id: u64,
+    pub room: String,
+    pub player_id: i32,
+    pub player_name: String,
+    pub text: String,
+    pub sent_at: u128, // unix timestamp in milliseconds
+}
+
+#[derive(Default, Debug)]
+pub struct ChatState {
+    next_message_id: u64,
+    // Map of room name to the most recent messages, oldest first
+    history: HashMap<String, VecDeque<ChatMessage>>,
+    // Map of player uuid to timestamps of messages sent within the rate limit window
+    recent_messages: HashMap<Uuid, VecDeque<Instant>>,
+}
+
+impl ChatState {
+    pub fn is_rate_limited(&mut self, player_uuid: &Uuid, now: Instant) -> bool {
+        let sent = self.recent_messages.entry(*player_uuid).or_default();
+
+        while sent.front().is_some_and(|sent_at| now.duration_since(*sent_at) > CHAT_RATE_LIMIT_WINDOW) {
+            sent.pop_front();
+        }
+
+        if sent.len() >= CHAT_RATE_LIMIT_COUNT {
+            return true;
+        }
+
+        sent.push_back(now);
+        false
+    }
+
+    pub fn push(&mut self, room: &str, player_id: i32, player_name: String, text: String) -> ChatMessage {
+        self.next_message_id += 1;
+
+        let message = ChatMessage {
+            id: self.next_message_id,
+            room: room.to_string(),
+            player_id,
+            player_name,
+            text,
+            sent_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_millis()),
+        };
+
+        let history = self.history.entry(room.to_string()).or_default();
+        history.push_back(message.clone());
+        if history.len() > CHAT_HISTORY_LENGTH {
+            history.pop_front();
+        }
+
+        message
+    }
+
+    pub fn delete(&mut self, room: &str, message_id: u64) -> bool {
+        match self.history.get_mut(room) {
+            Some(history) => {
+                let length_before = history.len();
+                history.retain(|message| message.id != message_id);
+                history.len() != length_before
+            }
+            None => false,
+        }
+    }
+
+    pub fn history(&self, room: &str) -> Vec<ChatMessage> {
+        self.history.get(room).map(|history| history.iter().cloned().collect()).unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WsChatMessage {
+    pub player_uuid: Uuid,
+    pub game_uuid: Option<Uuid>, // None for the lobby room
+    pub text: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WsChatDelete {
+    pub player_uuid: Uuid,
+    pub game_uuid: Option<Uuid>, // None for the lobby room
+    pub message_id: u64,
+}
+
+fn chat_room_name(game_uuid: &Option<Uuid>) -> String {
+    match game_uuid {
+        Some(game_uuid) => game_uuid.to_string(),
+        None => LOBBY_ROOM.to_string(),
+    }
+}
+
+pub fn send_chat_history(data: &Data<AppState>, room: &str, player_uuid: &Uuid) {
+    let payload = json!({
+        "type": "chat_history",
+        "data": {
+            "room": room,
+            "messages": data.chat.read().history(room),
+        }
+    }).to_string();
+
+    data.rooms_state.read().unwrap().send_to_client(&player_uuid.to_string(), &payload);
+}
+
+pub async fn ws_chat_message(data: &Data<AppState>, chat_message_payload: &WsChatMessage) {
+    let player_uuid = &chat_message_payload.player_uuid;
+    let room = chat_room_name(&chat_message_payload.game_uuid);
+    let text = chat_message_payload.text.trim();
+
+    let error = if text.is_empty() {
+        Some("chat message is empty")
+    } else if text.chars().count() > CHAT_MESSAGE_MAX_LENGTH {
+        Some("chat message is too long")
+    } else if !data.rooms_state.read().unwrap().is_in_room(&room, &player_uuid.to_string()) {
+        Some("not in chat room")
+    } else if data.chat.write().is_rate_limited(player_uuid, Instant::now()) {
+        Some("too many chat messages, slow down")
+    } else {
+        None
+    };
+
+    if let Some(error) = error {
+        data.rooms_state.read().unwrap().send_to_client(&player_uuid.to_string(), &ws_error_payload("chat_message", error));
+        return;
+    }
+
+    let (player_id, player_name) = match data.players.read().get(player_uuid) {
+        Some(player_rwlock) => {
+            let player_read = player_rwlock.read();
+            (player_read.id, player_read.name.clone())
+        }
+        None => {
+            error_log(format!("player not found in ws_chat_message {}", player_uuid));
+            return;
+        }
+    };
+
+    let message = data.chat.write().push(&room, player_id, player_name, text.to_string());
+
+    data.rooms_state.read().unwrap().broadcast_to_room(
+        &room,
+        json!({
+            "type": "chat_message",
+            "data": message,
+        }).to_string().as_str(),
+        None
+    );
+}
+
+pub async fn ws_chat_delete(data: &Data<AppState>, chat_delete_payload: &WsChatDelete) {
+    let player_uuid = &chat_delete_payload.player_uuid;
+    let room = chat_room_name(&chat_delete_payload.game_uuid);
+
+    let is_moderator = data.players.read().get(player_uuid).is_some_and(|player_rwlock| player_rwlock.read().moderator);
+    let is_game_admin = chat_delete_payload.game_uuid.as_ref()
+        .and_then(|game_uuid| data.games.read().get(game_uuid).map(|game_rwlock| game_rwlock.read().admin_uuid == *player_uuid))
+        .unwrap_or(false);
+
+    if !is_moderator && !is_game_admin {
+        data.rooms_state.read().unwrap().send_to_client(&player_uuid.to_string(), &ws_error_payload("chat_delete", "only moderators or the game admin can delete chat messages"));
+        return;
+    }
+
+    if !data.chat.write().delete(&room, chat_delete_payload.message_id) {
+        error_log(format!("chat message {} not found in room {}", chat_delete_payload.message_id, room));
+        return;
+    }
+
+    data.rooms_state.read().unwrap().broadcast_to_room(
+        &room,
+        json!({
+            "type": "chat_message_deleted",
+            "data": {
+                "room": room,
+                "id": chat_delete_payload.message_id,
+            }
+        }).to_string().as_str(),
+        None
+    );
+}
+
+#[test]
+fn test_chat_rate_limit() {
+    let mut chat_state = ChatState::default();
+    let player_uuid = Uuid::new_v4();
+    let now = Instant::now();
+
+    for _ in 0..CHAT_RATE_LIMIT_COUNT {
+        assert!(!chat_state.is_rate_limited(&player_uuid, now));
+    }
+    assert!(chat_state.is_rate_limited(&player_uuid, now));
+    assert!(!chat_state.is_rate_limited(&player_uuid, now + CHAT_RATE_LIMIT_WINDOW + Duration::from_secs(1)));
+}
+
+#[test]
+fn test_chat_history_is_capped() {
+    let mut chat_state = ChatState::default();
+
+    for i in 0..CHAT_HISTORY_LENGTH + 5 {
+        chat_state.push(LOBBY_ROOM, 1, "aija".to_string(), i.to_string());
+    }
+
+    let history = chat_state.history(LOBBY_ROOM);
+    assert_eq!(history.len(), CHAT_HISTORY_LENGTH);
+    assert_eq!(history[0].text, "5");
+    assert!(chat_state.delete(LOBBY_ROOM, history[0].id));
+    assert_eq!(chat_state.history(LOBBY_ROOM).len(), CHAT_HISTORY_LENGTH - 1);
+}
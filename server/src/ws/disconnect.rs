@@ -0,0 +1,261 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use actix_web::web::Data;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+use crate::AppState;
+use crate::types::{DisconnectedSeat, DisconnectedSeatAction, GameStatus};
+use crate::util::error_log;
+use crate::game::bot::BotDifficulty;
+use crate::ws::game::{broadcast_automatic_turn, finish_game_if_over, pass_turn, play_bot_placement};
+use crate::ws::{broadcast_to_game_room, ws_error_payload};
+
+const DISCONNECT_GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WsVoteRemovePlayer {
+    pub player_uuid: Uuid,
+    pub game_uuid: Uuid,
+    pub target_player_id: i32,
+}
+
+// the player's running game, if they still hold a seat in it
+fn running_game_of(data: &Data<AppState>, player_uuid: &Uuid) -> Option<(Uuid, i32)> {
+    let (game_uuid, player_id) = data.players.read().get(player_uuid).and_then(|player_rwlock| {
+        let player_read = player_rwlock.read();
+        player_read.game_uuid.map(|game_uuid| (game_uuid, player_read.id))
+    })?;
+
+    let is_seated = data.games.read().get(&game_uuid).is_some_and(|game_rwlock| {
+        let game_read = game_rwlock.read();
+        game_read.status == GameStatus::InProgress && game_read.players.contains(player_uuid) && !game_read.forfeited.contains(player_uuid)
+    });
+
+    is_seated.then_some((game_uuid, player_id))
+}
+
+pub fn mark_player_disconnected(data: &Data<AppState>, player_uuid: &Uuid) {
+    let Some((game_uuid, player_id)) = running_game_of(data, player_uuid) else {
+        return;
+    };
+
+    if let Some(game_rwlock) = data.games.read().get(&game_uuid) {
+        game_rwlock.write().disconnected_seats.entry(*player_uuid).or_insert(DisconnectedSeat {
+            since: Instant::now(),
+            grace_period_over: false,
+            removal_votes: HashSet::new(),
+        });
+    }
+
+    broadcast_to_game_room(data, &game_uuid, json!({
+        "type": "player_disconnected",
+        "data": {
+            "game_uuid": game_uuid,
+            "player_id": player_id,
+            "grace_period_seconds": DISCONNECT_GRACE_PERIOD.as_secs(),
+        }
+    }));
+}
+
+pub fn mark_player_reconnected(data: &Data<AppState>, player_uuid: &Uuid) {
+    let Some((game_uuid, player_id)) = running_game_of(data, player_uuid) else {
+        return;
+    };
+
+    let was_disconnected = data.games.read().get(&game_uuid)
+        .is_some_and(|game_rwlock| game_rwlock.write().disconnected_seats.remove(player_uuid).is_some());
+    if !was_disconnected {
+        return;
+    }
+
+    // the reconnect window of the rooms state may have passed, the seat still belongs to the player
+    data.rooms_state.write().unwrap().join_room(&game_uuid.to_string(), &player_uuid.to_string());
+
+    broadcast_to_game_room(data, &game_uuid, json!({
+        "type": "player_reconnected",
+        "data": {
+            "game_uuid": game_uuid,
+            "player_id": player_id,
+        }
+    }));
+}
+
+fn eligible_voters(data: &Data<AppState>, game_uuid: &Uuid, target_uuid: &Uuid) -> Vec<Uuid> {
+    data.games.read().get(game_uuid).map(|game_rwlock| {
+        let game_read = game_rwlock.read();
+        let players_read = data.players.read();
+        eligible_voters_among(&game_read.players, &game_read.forfeited, &game_read.disconnected_seats, target_uuid, |uuid| {
+            players_read.get(uuid).is_none_or(|player_rwlock| player_rwlock.read().bot.is_some())
+        })
+    }).unwrap_or_default()
+}
+
+// players that can vote a disconnected seat out, everyone still playing and connected except the target and bots
+fn eligible_voters_among(seated: &[Uuid], forfeited: &[Uuid], disconnected_seats: &HashMap<Uuid, DisconnectedSeat>, target_uuid: &Uuid, is_bot: impl Fn(&Uuid) -> bool) -> Vec<Uuid> {
+    seated.iter()
+        .filter(|uuid| *uuid != target_uuid && !forfeited.contains(uuid) && !disconnected_seats.contains_key(uuid))
+        .filter(|uuid| !is_bot(uuid))
+        .copied()
+        .collect()
+}
+
+fn votes_needed(eligible_voter_count: usize) -> usize {
+    eligible_voter_count / 2 + 1
+}
+
+// marks the seats whose grace period ran out by now and returns them, a seat is only returned once
+fn end_grace_periods(disconnected_seats: &mut HashMap<Uuid, DisconnectedSeat>, now: Instant) -> Vec<Uuid> {
+    disconnected_seats.iter_mut()
+        .filter(|(_, seat)| !seat.grace_period_over && now.duration_since(seat.since) >= DISCONNECT_GRACE_PERIOD)
+        .map(|(player_uuid, seat)| {
+            seat.grace_period_over = true;
+            *player_uuid
+        })
+        .collect()
+}
+
+// announces seats whose grace period just ran out and plays the turn of a disconnected player to move
+pub fn handle_disconnected_seats(data: &Data<AppState>) {
+    let now = Instant::now();
+    let mut grace_periods_over: Vec<(Uuid, Uuid)> = Vec::new();
+    let mut automatic_turns: Vec<(Uuid, Uuid, DisconnectedSeatAction)> = Vec::new();
+
+    data.games.read().values().for_each(|game_rwlock| {
+        let mut game_write = game_rwlock.write();
+        if game_write.status != GameStatus::InProgress {
+            return;
+        }
+        let game_uuid = game_write.uuid;
+
+        end_grace_periods(&mut game_write.disconnected_seats, now).into_iter()
+            .for_each(|player_uuid| grace_periods_over.push((game_uuid, player_uuid)));
+
+        if let Some(player_uuid) = game_write.player_to_move {
+            if game_write.disconnected_seats.get(&player_uuid).is_some_and(|seat| seat.grace_period_over) {
+                automatic_turns.push((game_uuid, player_uuid, game_write.disconnected_seat));
+            }
+        }
+    });
+
+    for (game_uuid, player_uuid) in grace_periods_over {
+        let player_id = data.players.read().get(&player_uuid).map(|player_rwlock| player_rwlock.read().id);
+        broadcast_to_game_room(data, &game_uuid, json!({
+            "type": "player_disconnect_grace_period_over",
+            "data": {
+                "game_uuid": game_uuid,
+                "player_id": player_id,
+                "votes_needed": votes_needed(eligible_voters(data, &game_uuid, &player_uuid).len()),
+            }
+        }));
+    }
+
+    for (game_uuid, player_uuid, action) in automatic_turns {
+        let played = match action {
+            DisconnectedSeatAction::Bot => play_bot_placement(data, &game_uuid, &player_uuid, BotDifficulty::Medium) || pass_turn(data, &game_uuid, &player_uuid, false),
+            DisconnectedSeatAction::Skip => pass_turn(data, &game_uuid, &player_uuid, false),
+        };
+        if played {
+            broadcast_automatic_turn(data, &game_uuid, &player_uuid, "disconnected_seat_played", action.as_str());
+        }
+        finish_game_if_over(data, &game_uuid);
+    }
+}
+
+pub async fn ws_vote_remove_player(data: &Data<AppState>, vote_payload: &WsVoteRemovePlayer) {
+    let player_uuid = &vote_payload.player_uuid;
+    let game_uuid = &vote_payload.game_uuid;
+
+    let target_uuid = data.games.read().get(game_uuid).and_then(|game_rwlock| {
+        let game_read = game_rwlock.read();
+        let players_read = data.players.read();
+        game_read.players.iter()
+            .find(|uuid| players_read.get(uuid).is_some_and(|player_rwlock| player_rwlock.read().id == vote_payload.target_player_id))
+            .copied()
+    });
+    let Some(target_uuid) = target_uuid else {
+        data.rooms_state.read().unwrap().send_to_client(&player_uuid.to_string(), &ws_error_payload("vote_remove_player", "player is not seated in this game"));
+        return;
+    };
+
+    let eligible = eligible_voters(data, game_uuid, &target_uuid);
+    if !eligible.contains(player_uuid) {
+        data.rooms_state.read().unwrap().send_to_client(&player_uuid.to_string(), &ws_error_payload("vote_remove_player", "only connected players of this game can vote"));
+        return;
+    }
+
+    let votes = data.games.read().get(game_uuid).and_then(|game_rwlock| {
+        let mut game_write = game_rwlock.write();
+        let seat = game_write.disconnected_seats.get_mut(&target_uuid).filter(|seat| seat.grace_period_over)?;
+        seat.removal_votes.insert(*player_uuid);
+        Some(seat.removal_votes.iter().filter(|voter| eligible.contains(voter)).count())
+    });
+    let Some(votes) = votes else {
+        data.rooms_state.read().unwrap().send_to_client(&player_uuid.to_string(), &ws_error_payload("vote_remove_player", "player is not disconnected past the grace period"));
+        return;
+    };
+
+    let needed = votes_needed(eligible.len());
+    if votes < needed {
+        broadcast_to_game_room(data, game_uuid, json!({
+            "type": "remove_player_vote",
+            "data": {
+                "game_uuid": game_uuid,
+                "player_id": vote_payload.target_player_id,
+                "votes": votes,
+                "votes_needed": needed,
+            }
+        }));
+        return;
+    }
+
+    if !pass_turn(data, game_uuid, &target_uuid, true) {
+        error_log(format!("could not remove player {} from game {}", target_uuid, game_uuid));
+        return;
+    }
+
+    broadcast_to_game_room(data, game_uuid, json!({
+        "type": "player_removed",
+        "data": {
+            "game_uuid": game_uuid,
+            "player_id": vote_payload.target_player_id,
+        }
+    }));
+    finish_game_if_over(data, game_uuid);
+}
+
+#[test]
+fn test_votes_needed_is_a_majority_of_eligible_voters() {
+    assert_eq!(votes_needed(1), 1);
+    assert_eq!(votes_needed(2), 2);
+    assert_eq!(votes_needed(3), 2);
+    assert_eq!(votes_needed(4), 3);
+}
+
+#[test]
+fn test_eligible_voters_leave_out_target_forfeited_disconnected_and_bots() {
+    let [target, forfeited, disconnected, bot, voter] = [(); 5].map(|_| Uuid::new_v4());
+    let seated = [target, forfeited, disconnected, bot, voter];
+    let disconnected_seats: HashMap<Uuid, DisconnectedSeat> = [target, disconnected].into_iter()
+        .map(|uuid| (uuid, DisconnectedSeat { since: Instant::now(), grace_period_over: true, removal_votes: HashSet::new() }))
+        .collect();
+
+    let eligible = eligible_voters_among(&seated, &[forfeited], &disconnected_seats, &target, |uuid| *uuid == bot);
+    assert_eq!(eligible, vec![voter]);
+}
+
+#[test]
+fn test_grace_period_ends_once_per_seat() {
+    let since = Instant::now();
+    let (late, early) = (Uuid::new_v4(), Uuid::new_v4());
+    let mut disconnected_seats = HashMap::from([
+        (late, DisconnectedSeat { since, grace_period_over: false, removal_votes: HashSet::new() }),
+        (early, DisconnectedSeat { since: since + DISCONNECT_GRACE_PERIOD / 2, grace_period_over: false, removal_votes: HashSet::new() }),
+    ]);
+
+    assert!(end_grace_periods(&mut disconnected_seats, since + DISCONNECT_GRACE_PERIOD / 2).is_empty());
+    assert_eq!(end_grace_periods(&mut disconnected_seats, since + DISCONNECT_GRACE_PERIOD), vec![late]);
+    assert!(disconnected_seats[&late].grace_period_over);
+    assert!(!disconnected_seats[&early].grace_period_over);
+    assert!(end_grace_periods(&mut disconnected_seats, since + DISCONNECT_GRACE_PERIOD).is_empty());
+}
@@ -1,11 +1,27 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use actix_web::web::Data;
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use uuid::Uuid;
 use crate::AppState;
-use crate::game::{calculate_progress_gained, is_valid_hex_pair_placement, COLORS};
-use crate::types::{BoardHex, BoardHexPair};
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use crate::game::{aggregate_team_progress, matches_rack_hex_pair, placements_left_in_turn};
+use crate::game::bot::{choose_placement, BotDifficulty};
+use crate::game::clock::TimeoutAction;
+use crate::game::undo::PlacementSnapshot;
+use crate::rating::calculate_rating_changes;
+use crate::routes::analysis::analyze_for_player;
+use crate::routes::lobby::{broadcast_lobby_game_change, persist_game_status, LobbyGameChange};
+use crate::types::{BoardHex, BoardHexPair, Game, GameStatus, HexPair, Player, Progress};
 use crate::util::error_log;
+use crate::ws::ws_error_payload;
+use crate::ws::disconnect::handle_disconnected_seats;
+use crate::ws::game_writes::GameWrite;
+use crate::ws::view::{public_game_view, send_private_player_state};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct WsPlaceHexPair {
@@ -17,104 +33,522 @@ pub struct WsPlaceHexPair {
 }
 
 pub async fn ws_place_hex_pair(data: &Data<AppState>, place_hex_pair_payload: &WsPlaceHexPair) {
-    let games = data.games.read();
+    let board_hex_pair = [place_hex_pair_payload.hex1, place_hex_pair_payload.hex2];
+    let result = apply_placement(data, &place_hex_pair_payload.game_uuid, &place_hex_pair_payload.player_uuid, place_hex_pair_payload.hex_pair_index, board_hex_pair);
+
+    if let Err(error) = result {
+        data.rooms_state.read().unwrap().send_to_client(&place_hex_pair_payload.player_uuid.to_string(), &ws_error_payload("place_hex_pair", error));
+        return;
+    }
+
+    finish_game_if_over(data, &place_hex_pair_payload.game_uuid);
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WsRequestHint {
+    pub game_uuid: Uuid,
+    pub player_uuid: Uuid,
+    pub count: Option<usize>,
+}
+
+// only the requesting player sees the hint, hints are refused in rated games
+pub async fn ws_request_hint(data: &Data<AppState>, request_hint_payload: &WsRequestHint) {
+    let player_uuid = request_hint_payload.player_uuid.to_string();
+    let payload = match analyze_for_player(data, &request_hint_payload.game_uuid, &request_hint_payload.player_uuid, request_hint_payload.count) {
+        Ok(analysis) => json!({
+            "type": "hint",
+            "data": {
+                "game_uuid": request_hint_payload.game_uuid,
+                "analysis": analysis,
+            }
+        }).to_string(),
+        Err(error) => ws_error_payload("request_hint", error.message()),
+    };
+    data.rooms_state.read().unwrap().send_to_client(&player_uuid, &payload);
+}
 
-    if !games.contains_key(&place_hex_pair_payload.game_uuid) {
-        error_log(format!("game does not exist in state with uuid: {}", place_hex_pair_payload.game_uuid));
-        return
+// places a hex pair from the player's rack, a color reaching 18 earns an extra placement in the same turn,
+// otherwise the rack is refilled and the move goes to the next seat
+pub fn apply_placement(data: &Data<AppState>, game_uuid: &Uuid, player_uuid: &Uuid, hex_pair_index: usize, board_hex_pair: BoardHexPair) -> Result<(), &'static str> {
+    let games = data.games.read();
+    let game_rwlock = games.get(game_uuid).ok_or("game not found")?;
+    let mut game_write = game_rwlock.write();
+    if game_write.status != GameStatus::InProgress {
+        return Err("game is not in progress");
+    }
+    if game_write.player_to_move != Some(*player_uuid) {
+        return Err("it is not your turn");
     }
 
     let players_read = data.players.read();
-    let game_read = games.get(&place_hex_pair_payload.game_uuid).unwrap().read();
-
-    if !players_read.contains_key(&place_hex_pair_payload.player_uuid) {
-        error_log(format!("player does not exist in state with uuid: {}", place_hex_pair_payload.player_uuid));
-        return
-    }
-
-    // remove hex pair from players hex pair list and insert hex pair in board hex pair list
-    let player_rwlock = players_read.get(&place_hex_pair_payload.player_uuid).unwrap().clone();
-    let player_read = player_rwlock.read();
-    let mut player_hex_pairs = player_read.hex_pairs.clone();
-    match player_hex_pairs.clone().get(place_hex_pair_payload.hex_pair_index) {
-        Some(_player_hex_pair) => {
-            player_hex_pairs[place_hex_pair_payload.hex_pair_index] = None;
-            // TODO validate hex pair colors and index
-
-            match data.boards.read().get(&place_hex_pair_payload.game_uuid) {
-                Some(board) => {
-                    let board_hex_1 = BoardHex { ..place_hex_pair_payload.hex1 };
-                    let board_hex_2 = BoardHex { ..place_hex_pair_payload.hex2 };
-                    let board_hex_pair: BoardHexPair = [board_hex_1, board_hex_2];
-                    let board_read = board.read();
-                    if !is_valid_hex_pair_placement(&board_read, game_read.board_size, board_hex_pair) {
-                        error_log(format!("invalid hex pair placement {:?} on board {:?}", board_hex_pair, board_read));
-                    }
-                    drop(game_read);
-                    drop(board_read);
-                    let progress_gained = calculate_progress_gained(board.read().to_vec(), board_hex_pair);
-                    let total_progress = progress_gained.clone().sum(player_read.progress.clone());
-                    // increment progress values and check how many colors reaches genial, increment moves_in_turn if necessary
-                    let genial_count = COLORS.iter().fold(0, |mut acc, color| {
-                        acc += if total_progress.clone().is_genial(color.clone()) { 1 } else { 0 };
-                        acc
-                    });
-                    drop(player_read);
-                    let mut player_write = player_rwlock.write();
-                    player_write.moves_in_turn += genial_count - 1;
-                    player_write.progress = total_progress.clone();
-
-                    // draw random hex pairs from available hex pair list, insert into players hex pair list
-                    if player_write.moves_in_turn == 0 {
-                        let hex_pairs_in_bag = games.get(&place_hex_pair_payload.game_uuid).unwrap().read().hex_pairs_in_bag.clone();
-                        while let Some(empty_index) = player_write.hex_pairs.iter().position(|hex_pair| hex_pair.is_none()) {
-                            match hex_pairs_in_bag.clone().take_random_hex_pair() {
-                                Some(hex_pair) => {
-                                    player_write.hex_pairs[empty_index] = Some(hex_pair);
-                                }
-                                None => {
-                                    error_log(format!("hex_pairs_in_bag is empty for game: {}", place_hex_pair_payload.game_uuid));
-                                    break;
-                                }
-                            }
-                        }
-                    }
-
-                    let mut writable_board = board.write();
-                    writable_board.push(board_hex_1);
-                    writable_board.push(board_hex_2);
-                    drop(writable_board);
-                }
-                None => {
-                    error_log(format!("game does not exist in boards state: {}", place_hex_pair_payload.game_uuid));
-                }
+    let player_rwlock = players_read.get(player_uuid).ok_or("player not found")?;
+    let mut player_write = player_rwlock.write();
+    let hex_pair = player_write.hex_pairs.get(hex_pair_index).copied().flatten().ok_or("no hex pair in this rack slot")?;
+    if !matches_rack_hex_pair(hex_pair, board_hex_pair) {
+        return Err("hex pair colors do not match the rack");
+    }
+
+    let boards = data.boards.read();
+    let mut board_write = boards.get(game_uuid).ok_or("board not found")?.write();
+    let rules = game_write.variant.rules();
+    if !rules.is_valid_placement(&board_write, game_write.board_size, board_hex_pair) {
+        return Err("invalid hex pair placement");
+    }
+
+    let progress_gained = rules.progress_gained(&board_write, board_hex_pair);
+    let total_progress = progress_gained.sum(player_write.progress.clone());
+    // in team play the bonus is earned when the team's combined color reaches 18
+    let teammate_progresses: Vec<Progress> = game_write.teammates(player_uuid).iter()
+        .filter_map(|uuid| players_read.get(uuid).map(|teammate_rwlock| teammate_rwlock.read().progress.clone()))
+        .collect();
+    let team_total = |progress: &Progress| teammate_progresses.iter().fold(progress.clone(), |acc, other| acc.sum(other.clone()));
+    let ingenious_count = rules.bonus_moves(&team_total(&player_write.progress), &team_total(&total_progress));
+
+    game_write.last_placement = Some(PlacementSnapshot::new(&game_write, &player_write, board_hex_pair));
+    game_write.undo_accepted_by = None;
+    player_write.progress = total_progress;
+    player_write.hex_pairs[hex_pair_index] = None;
+    board_write.extend_from_slice(&board_hex_pair);
+    game_write.move_count += 1;
+    game_write.passes_in_a_row = 0;
+    let move_number = game_write.move_count;
+    persist_game_move(data, game_uuid, player_uuid, move_number, game_write.turn_count + 1, board_hex_pair, ingenious_count);
+
+    if let Some(placements_left) = placements_left_in_turn(player_write.moves_in_turn, ingenious_count) {
+        player_write.moves_in_turn = placements_left;
+    } else {
+        player_write.moves_in_turn = 0;
+        let draws = rules.draw_hex_pairs(&game_write.hex_pairs_in_bag, &mut player_write.hex_pairs);
+        persist_hex_pair_draws(data, game_uuid, player_uuid, move_number, draws);
+        game_write.end_turn(Instant::now());
+    }
+
+    send_private_player_state(data, &player_write);
+    send_teammate_state(data, &game_write, &player_write);
+
+    let player_id = player_write.id;
+    let progress = player_write.progress.clone();
+    drop(player_write);
+    drop(board_write);
+
+    let mut message = json!({
+        "type": "game_state_per_move",
+        "data": {
+            "games": {
+                game_uuid.to_string(): {
+                    "status": game_write.status,
+                    "board": boards.get(game_uuid).map(|board| board.read().clone()).unwrap_or_default(),
+                    "player_to_move": game_write.player_to_move.and_then(|uuid| players_read.get(&uuid).map(|player_rwlock| player_rwlock.read().id)),
+                    "clock": collect_clock_state(&game_write, &players_read),
+                },
+            },
+        }
+    });
+    let message_without_progress = message.to_string();
+    message["data"]["players"] = json!({ player_id.to_string(): { "progress": progress } });
+    let message_with_progress = message.to_string();
+
+    data.rooms_state.read().unwrap().broadcast_to_room_with(&game_uuid.to_string(), None, |client_id| {
+        let sees_progress = Uuid::parse_str(client_id).map_or(true, |viewer_uuid| game_write.can_see_progress(&viewer_uuid, player_uuid));
+        Some(if sees_progress { message_with_progress.clone() } else { message_without_progress.clone() })
+    });
+
+    Ok(())
+}
+
+// once a second: applies the timeout action of expired clocks and plays the turns of disconnected seats
+pub async fn start_turn_task(data: Data<AppState>) {
+    let mut interval = actix_web::rt::time::interval(Duration::from_secs(1));
+
+    loop {
+        interval.tick().await;
+
+        let now = Instant::now();
+        let expired: Vec<(Uuid, Uuid, TimeoutAction)> = data.games.read().values().filter_map(|game_rwlock| {
+            let game_read = game_rwlock.read();
+            let clock = game_read.clock.as_ref()?;
+            let player_uuid = game_read.player_to_move?;
+            (game_read.status == GameStatus::InProgress && clock.is_expired(&player_uuid, now))
+                .then_some((game_read.uuid, player_uuid, clock.time_control.timeoutAction))
+        }).collect();
+
+        for (game_uuid, player_uuid, action) in expired {
+            handle_turn_timeout(&data, &game_uuid, &player_uuid, action);
+            finish_game_if_over(&data, &game_uuid);
+        }
+
+        handle_disconnected_seats(&data);
+
+        // a bot makes one placement per tick, bonus placements follow on the next ticks
+        let bot_turns: Vec<(Uuid, Uuid, BotDifficulty)> = data.games.read().values().filter_map(|game_rwlock| {
+            let game_read = game_rwlock.read();
+            let player_uuid = game_read.player_to_move.filter(|_| game_read.status == GameStatus::InProgress)?;
+            let difficulty = data.players.read().get(&player_uuid)?.read().bot?;
+            Some((game_read.uuid, player_uuid, difficulty))
+        }).collect();
+
+        for (game_uuid, player_uuid, difficulty) in bot_turns {
+            if !play_bot_placement(&data, &game_uuid, &player_uuid, difficulty) && pass_turn(&data, &game_uuid, &player_uuid, false) {
+                broadcast_automatic_turn(&data, &game_uuid, &player_uuid, "bot_passed", difficulty.as_str());
             }
+            finish_game_if_over(&data, &game_uuid);
+        }
+    }
+}
+
+fn handle_turn_timeout(data: &Data<AppState>, game_uuid: &Uuid, player_uuid: &Uuid, action: TimeoutAction) {
+    // the player may have moved since the clock was checked, nothing is played for them then
+    let still_expired = data.games.read().get(game_uuid).is_some_and(|game_rwlock| {
+        let game_read = game_rwlock.read();
+        game_read.player_to_move == Some(*player_uuid) && game_read.clock.as_ref().is_some_and(|clock| clock.is_expired(player_uuid, Instant::now()))
+    });
+    if !still_expired {
+        return;
+    }
+
+    if action == TimeoutAction::RandomMove && play_random_placement(data, game_uuid, player_uuid) {
+        broadcast_automatic_turn(data, game_uuid, player_uuid, "turn_timeout", action.as_str());
+        return;
+    }
+
+    if pass_turn(data, game_uuid, player_uuid, action == TimeoutAction::Forfeit) {
+        broadcast_automatic_turn(data, game_uuid, player_uuid, "turn_timeout", action.as_str());
+    }
+}
+
+pub fn play_random_placement(data: &Data<AppState>, game_uuid: &Uuid, player_uuid: &Uuid) -> bool {
+    random_legal_placement(data, game_uuid, player_uuid)
+        .is_some_and(|(hex_pair_index, board_hex_pair)| apply_placement(data, game_uuid, player_uuid, hex_pair_index, board_hex_pair).is_ok())
+}
+
+pub fn play_bot_placement(data: &Data<AppState>, game_uuid: &Uuid, player_uuid: &Uuid, difficulty: BotDifficulty) -> bool {
+    bot_placement(data, game_uuid, player_uuid, difficulty)
+        .is_some_and(|(hex_pair_index, board_hex_pair)| apply_placement(data, game_uuid, player_uuid, hex_pair_index, board_hex_pair).is_ok())
+}
+
+// ends the turn without a placement, a forfeiting player is skipped for the rest of the game
+pub fn pass_turn(data: &Data<AppState>, game_uuid: &Uuid, player_uuid: &Uuid, forfeit: bool) -> bool {
+    let games = data.games.read();
+    let Some(game_rwlock) = games.get(game_uuid) else {
+        return false;
+    };
+    let mut game_write = game_rwlock.write();
+    if game_write.status != GameStatus::InProgress || game_write.forfeited.contains(player_uuid) {
+        return false;
+    }
+
+    if forfeit {
+        game_write.forfeited.push(*player_uuid);
+        game_write.disconnected_seats.remove(player_uuid);
+    } else if game_write.player_to_move == Some(*player_uuid) {
+        game_write.passes_in_a_row += 1;
+    } else {
+        return false;
+    }
+    game_write.last_placement = None;
+    game_write.undo_accepted_by = None;
+
+    if game_write.player_to_move == Some(*player_uuid) {
+        persist_turn_pass(data, game_uuid, player_uuid, game_write.turn_count + 1);
+        if let Some(player_rwlock) = data.players.read().get(player_uuid) {
+            let mut player_write = player_rwlock.write();
+            player_write.moves_in_turn = 0;
+            let draws = game_write.variant.rules().draw_hex_pairs(&game_write.hex_pairs_in_bag, &mut player_write.hex_pairs);
+            persist_hex_pair_draws(data, game_uuid, player_uuid, game_write.move_count, draws);
+            send_teammate_state(data, &game_write, &player_write);
+        }
+        game_write.end_turn(Instant::now());
+    }
+
+    true
+}
 
-            let player_read = player_rwlock.read();
-
-            data.rooms_state.read().unwrap().broadcast_to_room(
-                &place_hex_pair_payload.game_uuid.to_string(),
-                json!({
-                    "type": "game_state_per_move",
-                    "data": {
-                        "games": {
-                            &place_hex_pair_payload.game_uuid.to_string(): {
-                                "status": "in_progress",
-                                "board": data.boards.read().get(&place_hex_pair_payload.game_uuid).unwrap().read().iter().collect::<Vec<&BoardHex>>(),
-                            },
-                        },
-                        "players": {
-                            player_read.id.to_string(): {
-                                "progress": player_read.progress,
-                            },
-                        },
-                    }
-                }).to_string().as_str(),
-                None
-            );
+fn random_legal_placement(data: &Data<AppState>, game_uuid: &Uuid, player_uuid: &Uuid) -> Option<(usize, BoardHexPair)> {
+    let (board_size, rules) = data.games.read().get(game_uuid).map(|game_rwlock| {
+        let game_read = game_rwlock.read();
+        (game_read.board_size, game_read.variant.rules())
+    })?;
+    let hex_pairs = data.players.read().get(player_uuid)?.read().hex_pairs;
+    let board = data.boards.read().get(game_uuid)?.read().clone();
+
+    rules.legal_placements(&board, board_size, &hex_pairs).choose(&mut thread_rng()).copied()
+}
+
+fn bot_placement(data: &Data<AppState>, game_uuid: &Uuid, player_uuid: &Uuid, difficulty: BotDifficulty) -> Option<(usize, BoardHexPair)> {
+    let (board_size, rules) = data.games.read().get(game_uuid).map(|game_rwlock| {
+        let game_read = game_rwlock.read();
+        (game_read.board_size, game_read.variant.rules())
+    })?;
+    let (hex_pairs, progress) = data.players.read().get(player_uuid).map(|player_rwlock| {
+        let player_read = player_rwlock.read();
+        (player_read.hex_pairs, player_read.progress.clone())
+    })?;
+    let board = data.boards.read().get(game_uuid)?.read().clone();
+
+    choose_placement(rules, difficulty, &board, board_size, &progress, &hex_pairs, &mut thread_rng())
+}
+
+// partners see each other's rack and progress, whatever the show_progress setting of the game
+pub fn send_teammate_state(data: &Data<AppState>, game: &Game, player: &Player) {
+    let teammates = game.teammates(&player.uuid);
+    if teammates.is_empty() {
+        return;
+    }
+
+    let payload = json!({
+        "type": "teammate_state",
+        "data": {
+            "game_uuid": game.uuid,
+            "player_id": player.id,
+            "hexPairs": player.hex_pairs,
+            "progress": player.progress,
         }
-        None => {
-            error_log(format!("player {} does not have a hex pair in this slot index: {}", place_hex_pair_payload.player_uuid, place_hex_pair_payload.hex_pair_index));
+    }).to_string();
+    let rooms_state = data.rooms_state.read().unwrap();
+    for teammate_uuid in teammates {
+        rooms_state.send_to_client(&teammate_uuid.to_string(), &payload);
+    }
+}
+
+// tells the game room that the server played or skipped a turn on the player's behalf
+pub fn broadcast_automatic_turn(data: &Data<AppState>, game_uuid: &Uuid, player_uuid: &Uuid, message_type: &str, action: &str) {
+    let games = data.games.read();
+    let Some(game_rwlock) = games.get(game_uuid) else {
+        return;
+    };
+    let game_read = game_rwlock.read();
+    let players_read = data.players.read();
+    let player_id = |uuid: &Uuid| players_read.get(uuid).map(|player_rwlock| player_rwlock.read().id);
+
+    data.rooms_state.read().unwrap().broadcast_to_room(
+        &game_uuid.to_string(),
+        json!({
+            "type": message_type,
+            "data": {
+                "game_uuid": game_uuid,
+                "player_id": player_id(player_uuid),
+                "action": action,
+                "player_to_move": game_read.player_to_move.as_ref().and_then(player_id),
+                "clock": collect_clock_state(&game_read, &players_read),
+            }
+        }).to_string().as_str(),
+        None
+    );
+}
+
+// remaining time per player id, in milliseconds, null when the game has no time control
+pub fn collect_clock_state(game: &Game, players: &HashMap<Uuid, Arc<RwLock<Player>>>) -> serde_json::Value {
+    let Some(clock) = &game.clock else {
+        return serde_json::Value::Null;
+    };
+    let now = Instant::now();
+    let player_to_move = game.player_to_move.as_ref();
+
+    let time_banks = game.players.iter().filter_map(|uuid| {
+        let player_id = players.get(uuid)?.read().id;
+        let time_bank = clock.time_bank(uuid, player_to_move, now).map(|time_bank| time_bank.as_millis() as u64);
+        Some((player_id.to_string(), json!(time_bank)))
+    }).collect::<serde_json::Map<String, serde_json::Value>>();
+
+    json!({
+        "time_control": clock.time_control,
+        "time_banks_ms": time_banks,
+        "turn_remaining_ms": player_to_move
+            .and_then(|uuid| clock.turn_deadline(uuid))
+            .map(|deadline| deadline.saturating_duration_since(now).as_millis() as u64),
+    })
+}
+
+fn persist_game_move(data: &Data<AppState>, game_uuid: &Uuid, player_uuid: &Uuid, move_number: u32, turn_number: u32, board_hex_pair: BoardHexPair, ingenious_count: usize) {
+    data.game_writes.lock().push(&data.postgres_pool, game_uuid, GameWrite::Move { player_uuid: *player_uuid, move_number, turn_number, board_hex_pair, ingenious_count });
+}
+
+// turns without a placement are kept too, otherwise the seat that played a turn is lost
+fn persist_turn_pass(data: &Data<AppState>, game_uuid: &Uuid, player_uuid: &Uuid, turn_number: u32) {
+    data.game_writes.lock().push(&data.postgres_pool, game_uuid, GameWrite::Pass { player_uuid: *player_uuid, turn_number });
+}
+
+pub fn persist_hex_pair_draws(data: &Data<AppState>, game_uuid: &Uuid, player_uuid: &Uuid, move_number: u32, draws: Vec<(usize, HexPair)>) {
+    if draws.is_empty() {
+        return;
+    }
+    data.game_writes.lock().push(&data.postgres_pool, game_uuid, GameWrite::Draws { player_uuid: *player_uuid, move_number, draws });
+}
+
+pub fn persist_seat_order(data: &Data<AppState>, game_uuid: &Uuid, players: &[Uuid]) {
+    let postgres_pool = data.postgres_pool.clone();
+    let game_uuid = game_uuid.to_string();
+    let players: Vec<String> = players.iter().map(|player_uuid| player_uuid.to_string()).collect();
+
+    actix_web::rt::spawn(async move {
+        for (seat, player_uuid) in players.iter().enumerate() {
+            let query_result = sqlx::query("INSERT INTO game_seat (game_uuid, player_uuid, seat) VALUES ($1, $2, $3)")
+                .bind(&game_uuid)
+                .bind(player_uuid)
+                .bind(seat as i32)
+                .execute(&postgres_pool)
+                .await;
+            if let Err(error) = query_result {
+                error_log(format!("database error (persist_seat_order) for game {} {}", game_uuid, error));
+            }
         }
+    });
+}
+
+struct GameResult {
+    player_uuid: Uuid,
+    player_id: i32,
+    rank: usize,
+    progress: Progress,
+    rating_before: f64,
+    rating_after: f64,
+}
+
+// ends the game once nobody can move anymore, ranks the players and updates ratings for rated games
+pub fn finish_game_if_over(data: &Data<AppState>, game_uuid: &Uuid) {
+    let games = data.games.read();
+    let Some(game_rwlock) = games.get(game_uuid) else {
+        return;
+    };
+    let mut game_write = game_rwlock.write();
+    if game_write.status != GameStatus::InProgress {
+        return;
     }
-}
\ No newline at end of file
+
+    let rules = game_write.variant.rules();
+    let players_read = data.players.read();
+    let seated: Vec<_> = game_write.players.iter().filter_map(|uuid| players_read.get(uuid).cloned()).collect();
+    let teams: Vec<Option<usize>> = seated.iter().map(|player_rwlock| game_write.team_of(&player_rwlock.read().uuid)).collect();
+    let progresses = aggregate_team_progress(&seated.iter().map(|player_rwlock| player_rwlock.read().progress.clone()).collect::<Vec<Progress>>(), &teams);
+    let seat_forfeited: Vec<bool> = seated.iter().map(|player_rwlock| game_write.forfeited.contains(&player_rwlock.read().uuid)).collect();
+    let active_count = seat_forfeited.iter().filter(|has_forfeited| !**has_forfeited).count();
+    // a team is only out when every partner forfeited, it plays on with the ones left
+    let side = |seat: usize| teams[seat].unwrap_or(seat);
+    let forfeited: Vec<bool> = (0..seated.len()).map(|seat| (0..seated.len()).filter(|other| side(*other) == side(seat)).all(|other| seat_forfeited[other])).collect();
+    let active_sides: HashSet<usize> = (0..seated.len()).filter(|seat| !forfeited[*seat]).map(side).collect();
+    let side_count: HashSet<usize> = (0..seated.len()).map(side).collect();
+    let racks_empty = game_write.hex_pairs_in_bag.is_empty()
+        && seated.iter().all(|player_rwlock| player_rwlock.read().hex_pairs.iter().all(|hex_pair| hex_pair.is_none()));
+    let game_over = rules.is_game_decided(side_count.len(), active_sides.len(), game_write.passes_in_a_row, active_count)
+        || match data.boards.read().get(game_uuid) {
+            Some(board) => rules.is_game_over(&board.read(), game_write.board_size, &progresses, racks_empty),
+            None => false,
+        };
+    if !game_over {
+        return;
+    }
+
+    if let Err(error) = game_write.transition_to(GameStatus::Ended) {
+        error_log(format!("could not end game {} {}", game_uuid, error));
+        return;
+    }
+    data.game_writes.lock().close(game_uuid);
+    let rated = game_write.rated;
+    drop(game_write);
+    drop(games);
+
+    let ranks = rules.rank_players(&progresses, &forfeited);
+    let ratings_before: Vec<f64> = seated.iter().map(|player_rwlock| player_rwlock.read().rating).collect();
+    let rating_changes = if rated { calculate_rating_changes(&ratings_before, &ranks) } else { vec![0.0; seated.len()] };
+
+    let results: Vec<GameResult> = seated.iter().enumerate().map(|(i, player_rwlock)| {
+        let mut player_write = player_rwlock.write();
+        player_write.rating = ratings_before[i] + rating_changes[i];
+        GameResult {
+            player_uuid: player_write.uuid,
+            player_id: player_write.id,
+            rank: ranks[i],
+            progress: progresses[i].clone(),
+            rating_before: ratings_before[i],
+            rating_after: player_write.rating,
+        }
+    }).collect();
+    drop(players_read);
+
+    persist_game_status(data, game_uuid, GameStatus::Ended);
+    persist_game_results(data, game_uuid, &results, rated);
+
+    data.rooms_state.read().unwrap().broadcast_to_room(
+        &game_uuid.to_string(),
+        json!({
+            "type": "game_ended",
+            "data": {
+                "game_uuid": game_uuid,
+                "rated": rated,
+                "results": results.iter().map(|result| json!({
+                    "player_id": result.player_id,
+                    "rank": result.rank,
+                    "progress": result.progress,
+                    "rating_before": result.rating_before,
+                    "rating_after": result.rating_after,
+                    "score_details": rules.score_details(&result.progress),
+                })).collect::<Vec<serde_json::Value>>(),
+            }
+        }).to_string().as_str(),
+        None
+    );
+    broadcast_lobby_game_change(data, LobbyGameChange::Updated, game_uuid);
+}
+
+fn persist_game_results(data: &Data<AppState>, game_uuid: &Uuid, results: &[GameResult], rated: bool) {
+    let postgres_pool = data.postgres_pool.clone();
+    let game_uuid = game_uuid.to_string();
+    let results: Vec<(String, i32, String, f64, f64)> = results.iter().map(|result| (
+        result.player_uuid.to_string(),
+        result.rank as i32,
+        json!(result.progress).to_string(),
+        result.rating_before,
+        result.rating_after,
+    )).collect();
+
+    actix_web::rt::spawn(async move {
+        for (player_uuid, rank, progress, rating_before, rating_after) in results {
+            let query_result = sqlx::query("INSERT INTO game_result (game_uuid, player_uuid, rank, progress) VALUES ($1, $2, $3, $4::jsonb)")
+                .bind(&game_uuid)
+                .bind(&player_uuid)
+                .bind(rank)
+                .bind(&progress)
+                .execute(&postgres_pool)
+                .await;
+            if let Err(error) = query_result {
+                error_log(format!("database error (persist_game_results) for game {} {}", game_uuid, error));
+            }
+
+            if !rated {
+                continue;
+            }
+
+            let query_result = sqlx::query("INSERT INTO rating_history (player_uuid, game_uuid, rating_before, rating_after) VALUES ($1, $2, $3, $4)")
+                .bind(&player_uuid)
+                .bind(&game_uuid)
+                .bind(rating_before)
+                .bind(rating_after)
+                .execute(&postgres_pool)
+                .await;
+            if let Err(error) = query_result {
+                error_log(format!("database error (persist_game_results) for game {} {}", game_uuid, error));
+            }
+
+            let query_result = sqlx::query("UPDATE player SET rating = $1 WHERE uuid = $2")
+                .bind(rating_after)
+                .bind(&player_uuid)
+                .execute(&postgres_pool)
+                .await;
+            if let Err(error) = query_result {
+                error_log(format!("database error (persist_game_results) for player {} {}", player_uuid, error));
+            }
+        }
+    });
+}
+
+// game state without any player's hex pairs, progress is left out for the players the viewer may not see yet
+pub fn collect_public_game_state(data: &Data<AppState>, game_uuid: &Uuid, viewer_uuid: &Uuid) -> serde_json::Value {
+    let games = data.games.read();
+    let Some(game_rwlock) = games.get(game_uuid) else {
+        error_log(format!("game not found while collecting public game state: {}", game_uuid));
+        return json!({});
+    };
+    let board = data.boards.read().get(game_uuid).map(|board| board.read().clone()).unwrap_or_default();
+    let view = public_game_view(&game_rwlock.read(), &data.players.read(), &board, viewer_uuid);
+    view
+}
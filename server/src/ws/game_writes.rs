@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use parking_lot::Mutex;
+use sqlx::{Pool, Postgres};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use uuid::Uuid;
+use crate::types::{BoardHexPair, HexPair};
+use crate::util::error_log;
+
+// a game's moves, draws, passes and undos are written one after the other, in the order they were queued.
+// they are queued while the game's write lock is held, so an undo can never delete a move before it was inserted
+pub type GameWrites = Arc<Mutex<GameWriteQueues>>;
+
+#[derive(Debug)]
+pub enum GameWrite {
+    Move { player_uuid: Uuid, move_number: u32, turn_number: u32, board_hex_pair: BoardHexPair, ingenious_count: usize },
+    // draws are (rack index, hex pair), move number 0 is the initial deal
+    Draws { player_uuid: Uuid, move_number: u32, draws: Vec<(usize, HexPair)> },
+    Pass { player_uuid: Uuid, turn_number: u32 },
+    UndonePlacement { player_uuid: Uuid, move_number: u32 },
+}
+
+#[derive(Default, Debug)]
+pub struct GameWriteQueues {
+    queues: HashMap<Uuid, UnboundedSender<GameWrite>>,
+}
+
+impl GameWriteQueues {
+    // the first write of a game starts the task that works through its queue
+    pub fn push(&mut self, postgres_pool: &Pool<Postgres>, game_uuid: &Uuid, game_write: GameWrite) {
+        let sender = self.queues.entry(*game_uuid).or_insert_with(|| {
+            let (sender, mut receiver) = unbounded_channel();
+            let postgres_pool = postgres_pool.clone();
+            let game_uuid = *game_uuid;
+            actix_web::rt::spawn(async move {
+                while let Some(game_write) = receiver.recv().await {
+                    execute_game_write(&postgres_pool, &game_uuid, game_write).await;
+                }
+            });
+            sender
+        });
+        if let Err(error) = sender.send(game_write) {
+            error_log(format!("could not queue write for game {} {:?}", game_uuid, error.0));
+        }
+    }
+
+    // the task finishes the writes already queued and stops
+    pub fn close(&mut self, game_uuid: &Uuid) {
+        self.queues.remove(game_uuid);
+    }
+}
+
+async fn execute_game_write(postgres_pool: &Pool<Postgres>, game_uuid: &Uuid, game_write: GameWrite) {
+    let game_uuid = game_uuid.to_string();
+    match game_write {
+        GameWrite::Move { player_uuid, move_number, turn_number, board_hex_pair, ingenious_count } => {
+            let [hex1, hex2] = board_hex_pair;
+            let query_result = sqlx::query(r#"INSERT INTO game_move (game_uuid, player_uuid, move_number, hex1_x, hex1_y, hex1_color, hex2_x, hex2_y, hex2_color, ingenious_count, turn_number)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)"#)
+                .bind(&game_uuid)
+                .bind(player_uuid.to_string())
+                .bind(move_number as i32)
+                .bind(hex1.x as i32)
+                .bind(hex1.y as i32)
+                .bind(hex1.color as i32)
+                .bind(hex2.x as i32)
+                .bind(hex2.y as i32)
+                .bind(hex2.color as i32)
+                .bind(ingenious_count as i32)
+                .bind(turn_number as i32)
+                .execute(postgres_pool)
+                .await;
+            if let Err(error) = query_result {
+                error_log(format!("database error (persist_game_move) for game {} {}", game_uuid, error));
+            }
+        }
+        GameWrite::Draws { player_uuid, move_number, draws } => {
+            for (rack_index, hex_pair) in draws {
+                let query_result = sqlx::query("INSERT INTO game_draw (game_uuid, player_uuid, move_number, rack_index, color1, color2) VALUES ($1, $2, $3, $4, $5, $6)")
+                    .bind(&game_uuid)
+                    .bind(player_uuid.to_string())
+                    .bind(move_number as i32)
+                    .bind(rack_index as i32)
+                    .bind(hex_pair[0] as i32)
+                    .bind(hex_pair[1] as i32)
+                    .execute(postgres_pool)
+                    .await;
+                if let Err(error) = query_result {
+                    error_log(format!("database error (persist_hex_pair_draws) for game {} {}", game_uuid, error));
+                }
+            }
+        }
+        GameWrite::Pass { player_uuid, turn_number } => {
+            let query_result = sqlx::query("INSERT INTO game_pass (game_uuid, player_uuid, turn_number) VALUES ($1, $2, $3)")
+                .bind(&game_uuid)
+                .bind(player_uuid.to_string())
+                .bind(turn_number as i32)
+                .execute(postgres_pool)
+                .await;
+            if let Err(error) = query_result {
+                error_log(format!("database error (persist_turn_pass) for game {} {}", game_uuid, error));
+            }
+        }
+        // the placement and the hex pairs drawn after it are no longer part of the game's record
+        GameWrite::UndonePlacement { player_uuid, move_number } => {
+            for query in ["DELETE FROM game_move WHERE game_uuid = $1 AND player_uuid = $2 AND move_number = $3", "DELETE FROM game_draw WHERE game_uuid = $1 AND player_uuid = $2 AND move_number = $3"] {
+                let query_result = sqlx::query(query)
+                    .bind(&game_uuid)
+                    .bind(player_uuid.to_string())
+                    .bind(move_number as i32)
+                    .execute(postgres_pool)
+                    .await;
+                if let Err(error) = query_result {
+                    error_log(format!("database error (persist_undone_placement) for game {} {}", game_uuid, error));
+                }
+            }
+        }
+    }
+}
@@ -1,14 +1,18 @@
-use std::collections::HashMap;
 use std::sync::{Arc};
 use actix_web::web::Data;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use rand::{thread_rng};
-use rand::seq::SliceRandom;
 use uuid::Uuid;
 use crate::AppState;
-use crate::routes::lobby::collect_lobby_game_player_state;
-use crate::types::{Player};
+use crate::routes::lobby::{broadcast_lobby_game_change, collect_lobby_game_player_state, leave_game, persist_game_status, LobbyGameChange};
+use crate::ws::{broadcast_to_game_room, ws_error_payload};
+use crate::ws::game::{collect_public_game_state, persist_hex_pair_draws, persist_seat_order, send_teammate_state};
+use crate::ws::chat::send_chat_history;
+use crate::ws::matchmaking::leave_matchmaking;
+use crate::ws::view::{broadcast_public_game_state, send_private_player_state};
+use std::time::Instant;
+use crate::game::clock::GameClock;
+use crate::types::{Game, GameStatus, TEAM_COUNT};
 use crate::util::error_log;
 use parking_lot::RwLock;
 
@@ -21,6 +25,7 @@ struct WsRegister {
 pub struct WsJoinGame {
     player_uuid: Uuid,
     game_uuid: Uuid,
+    invite_code: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -29,6 +34,13 @@ pub struct WsLeaveGame {
     pub game_uuid: Uuid,
 }
 
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WsSpectateGame {
+    pub player_uuid: Uuid,
+    pub game_uuid: Uuid,
+    pub invite_code: Option<String>,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct WsReadyChange {
     pub player_uuid: Uuid,
@@ -36,6 +48,13 @@ pub struct WsReadyChange {
     pub ready: bool,
 }
 
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WsChooseTeam {
+    pub player_uuid: Uuid,
+    pub game_uuid: Uuid,
+    pub team: usize,
+}
+
 // pub async fn ws_register(app_state: &Data<AppState>, session: &mut actix_ws::Session, ws_register_payload: &WsRegister) {
 //
 // }
@@ -44,194 +63,366 @@ pub async fn ws_join_game(app_state: &Data<AppState>, session: &mut actix_ws::Se
     let player_uuid = &join_game_data.player_uuid;
     let game_uuid = &join_game_data.game_uuid;
 
-    app_state.rooms_state.write().unwrap().join_room(&game_uuid.to_string(), &player_uuid.to_string());
+    let previous_game_uuid = match app_state.players.read().get(player_uuid) {
+        Some(player_rwlock) => player_rwlock.read().game_uuid,
+        None => {
+            error_log(format!("player not found (ws_join_game) with uuid: {}", player_uuid));
+            return;
+        }
+    };
 
-    match app_state.players.read().get(&player_uuid) {
+    let previous_game_uuid = previous_game_uuid.filter(|uuid| uuid != game_uuid);
+    let can_leave_previous_game = previous_game_uuid
+        .and_then(|previous_game_uuid| app_state.games.read().get(&previous_game_uuid).map(|game_rwlock| game_rwlock.read().can_leave(player_uuid)))
+        .unwrap_or(true);
+    if !can_leave_previous_game {
+        if session.text(ws_error_payload("join_game", "a running game can not be left")).await.is_err() {
+            error_log("ws connection closed (ws_join_game)".to_string());
+        }
+        return;
+    }
+
+    // the new seat is taken under the game lock before the previous game is left, so a failed join keeps the old seat
+    let join_result = match app_state.games.read().get(game_uuid) {
+        Some(game_rwlock) => {
+            let mut game_write = game_rwlock.write();
+            check_game_joinable(&game_write, player_uuid, join_game_data.invite_code.as_deref()).map(|_| {
+                let newly_seated = !game_write.players.contains(player_uuid);
+                if newly_seated {
+                    game_write.players.push(*player_uuid);
+                    game_write.assign_team(player_uuid);
+                    game_write.spectators.retain(|uuid| uuid != player_uuid);
+                }
+                newly_seated
+            })
+        }
         None => {
-            error_log(format!("player not found (api_lobby_game_join) with uuid: {}", player_uuid));
+            error_log(format!("game not found (JoinGameRequest) with uuid: {}", &game_uuid));
+            return;
         }
-        Some(player_rwlock) => {
-            let mut player = player_rwlock.write();
-            player.game_uuid = Some(*game_uuid);
-            player.ready = false;
+    };
+
+    let newly_seated = match join_result {
+        Ok(newly_seated) => newly_seated,
+        Err(error) => {
+            if session.text(ws_error_payload("join_game", error)).await.is_err() {
+                error_log("ws connection closed (ws_join_game)".to_string());
+            }
+            return;
+        }
+    };
+
+    // the previous game may have started since the check above, the new seat is given up again then
+    if let Some(previous_game_uuid) = previous_game_uuid {
+        if let Err(error) = leave_previous_game(app_state, player_uuid, &previous_game_uuid) {
+            if newly_seated {
+                if let Some(game_rwlock) = app_state.games.read().get(game_uuid) {
+                    game_rwlock.write().remove_player(player_uuid);
+                }
+            }
+            if session.text(ws_error_payload("join_game", error)).await.is_err() {
+                error_log("ws connection closed (ws_join_game)".to_string());
+            }
+            return;
         }
     }
+    leave_matchmaking(app_state, player_uuid);
+
+    if let Some(player_rwlock) = app_state.players.read().get(player_uuid) {
+        let mut player = player_rwlock.write();
+        player.game_uuid = Some(*game_uuid);
+        player.ready = false;
+    }
+
+    app_state.rooms_state.write().unwrap().join_room(&game_uuid.to_string(), &player_uuid.to_string());
+    send_chat_history(app_state, &game_uuid.to_string(), player_uuid);
 
-    if let Some(game) = app_state.games.read().get(game_uuid) {
-        let mut game_write = game.write();
-        if !game_write.players.contains(player_uuid) {
-            game_write.players.push(*player_uuid);
+    let payload = json!({
+        "type": "player_joined",
+        "data": {
+            "games": json!({
+                &game_uuid.to_string(): {
+                    "players": collect_lobby_game_player_state(app_state, game_uuid),
+                }
+            })
         }
-        drop(game_write);
+    }).to_string();
+
+    broadcast_lobby_game_change(app_state, LobbyGameChange::Updated, game_uuid);
+
+    if session.text(payload).await.is_err() {
+        error_log("ws connection closed (ws_join_game)".to_string());
+    }
+}
+
+fn check_game_joinable(game: &Game, player_uuid: &Uuid, invite_code: Option<&str>) -> Result<(), &'static str> {
+    if game.players.contains(player_uuid) {
+        Ok(())
+    } else if !game.can_enter(player_uuid, invite_code) {
+        Err("invalid invite code")
+    } else if game.status != GameStatus::Created {
+        Err("game has already started")
+    } else if game.players.len() >= game.player_count as usize {
+        Err("game is full")
+    } else {
+        Ok(())
+    }
+}
+
+// a player can only be seated in one game, joining another one leaves the previous game, unless it is running
+pub fn leave_previous_game(app_state: &Data<AppState>, player_uuid: &Uuid, previous_game_uuid: &Uuid) -> Result<(), &'static str> {
+    if !app_state.games.read().contains_key(previous_game_uuid) {
+        return Ok(());
+    }
 
-        let payload = format!("{}", json!({
-            "type": "player_joined",
+    leave_game(app_state, player_uuid, previous_game_uuid)?;
+
+    app_state.rooms_state.read().unwrap().broadcast_to_room(
+        &previous_game_uuid.to_string(),
+        json!({
+            "type": "player_left",
             "data": {
                 "games": json!({
-                    &game_uuid.to_string(): {
-                        "players": collect_lobby_game_player_state(app_state, &game_uuid),
+                    &previous_game_uuid.to_string(): {
+                        "players": collect_lobby_game_player_state(app_state, previous_game_uuid),
                     }
                 })
             }
-        }));
+        }).to_string().as_str(),
+        None
+    );
 
-        if let Err(_) = session.text(payload).await {
-            error_log(format!("ws connection closed (ws_join_game)"));
-        }
-    } else {
-        error_log(format!("game not found (JoinGameRequest) with uuid: {}", &game_uuid));
-    }
+    broadcast_lobby_game_change(app_state, LobbyGameChange::Updated, previous_game_uuid);
+    Ok(())
 }
 
 pub async fn ws_leave_game(app_state: &Data<AppState>, session: &mut actix_ws::Session, leave_game_data: &WsLeaveGame) {
     let player_uuid = &leave_game_data.player_uuid;
     let game_uuid = &leave_game_data.game_uuid;
 
-    app_state.rooms_state.write().unwrap().leave_room(&game_uuid.to_string(), &player_uuid.to_string());
+    if !app_state.games.read().contains_key(game_uuid) {
+        error_log(format!("game not found while leaving the game {}", &game_uuid));
+        return;
+    }
 
-    match app_state.players.read().get(&player_uuid) {
-        Some(player) => {
-            let mut player_write = player.write();
-            player_write.game_uuid = None;
-            player_write.ready = false;
+    if let Err(error) = leave_game(app_state, player_uuid, game_uuid) {
+        if session.text(ws_error_payload("leave_game", error)).await.is_err() {
+            error_log("ws connection closed (ws_leave_game)".to_string());
         }
-        None => {
-            error_log(format!("player not found while leaving the game {}", &player_uuid));
+        return;
+    }
+
+    let payload = json!({
+        "type": "player_left",
+        "data": {
+            "games": json!({
+                &game_uuid.to_string(): {
+                    "players": collect_lobby_game_player_state(app_state, game_uuid),
+                }
+            })
         }
+    }).to_string();
+
+    broadcast_lobby_game_change(app_state, LobbyGameChange::Updated, game_uuid);
+
+    if session.text(payload).await.is_err() {
+        error_log("ws connection closed (ws_leave_game)".to_string());
     }
+}
+
+pub async fn ws_spectate_game(app_state: &Data<AppState>, session: &mut actix_ws::Session, spectate_game_data: &WsSpectateGame) {
+    let player_uuid = &spectate_game_data.player_uuid;
+    let game_uuid = &spectate_game_data.game_uuid;
 
-    if let Some(game) = app_state.games.read().get(&game_uuid) {
-        let mut game_write = game.write();
-        if let Some(pos) = game_write.players.iter().position(|uuid| *uuid == *player_uuid) {
-            game_write.players.remove(pos);
+    let spectate_result = match app_state.games.read().get(game_uuid) {
+        Some(game) => {
+            let mut game_write = game.write();
+            if !game_write.allow_spectators {
+                Err("spectators are not allowed in this game")
+            } else if !game_write.can_enter(player_uuid, spectate_game_data.invite_code.as_deref()) {
+                Err("invalid invite code")
+            } else {
+                if !game_write.players.contains(player_uuid) && !game_write.spectators.contains(player_uuid) {
+                    game_write.spectators.push(*player_uuid);
+                }
+                Ok(())
+            }
         }
-        drop(game_write);
+        None => {
+            error_log(format!("game not found (ws_spectate_game) with uuid: {}", &game_uuid));
+            return;
+        }
+    };
 
-        let payload = format!("{}", json!({
-            "type": "player_left",
+    let payload = if let Err(error) = spectate_result {
+        ws_error_payload("spectate_game", error)
+    } else {
+        leave_matchmaking(app_state, player_uuid);
+        app_state.rooms_state.write().unwrap().join_room(&game_uuid.to_string(), &player_uuid.to_string());
+        send_chat_history(app_state, &game_uuid.to_string(), player_uuid);
+
+        json!({
+            "type": "game_state",
             "data": {
-                "games": json!({
-                    &game_uuid.to_string(): {
-                        "players": collect_lobby_game_player_state(app_state, &game_uuid),
-                    }
-                })
+                "games": {
+                    &game_uuid.to_string(): collect_public_game_state(app_state, game_uuid, player_uuid),
+                },
             }
-        }));
+        }).to_string()
+    };
 
-        if let Err(_) = session.text(payload).await {
-            error_log(format!("ws connection closed (ws_leave_game)"));
-        }
-    } else {
-        error_log(format!("game not found while leaving the game {}", &game_uuid));
+    if session.text(payload).await.is_err() {
+        error_log("ws connection closed (ws_spectate_game)".to_string());
     }
 }
 
 pub async fn ws_ready_change(data: &Data<AppState>, ready_change_payload: &WsReadyChange) {
-    let players_read = data.players.read();
-    match players_read.get(&ready_change_payload.player_uuid) {
-        Some(player) => {
-            player.write().ready = ready_change_payload.ready;
+    let player_uuid = &ready_change_payload.player_uuid;
+    let game_uuid = &ready_change_payload.game_uuid;
+
+    let is_seated = data.games.read().get(game_uuid)
+        .is_some_and(|game_rwlock| game_rwlock.read().players.contains(player_uuid));
+    if !is_seated {
+        error_log(format!("player {} is not seated in game {} (ws_ready_change)", player_uuid, game_uuid));
+        return;
+    }
+
+    match data.players.read().get(player_uuid) {
+        Some(player_rwlock) => {
+            player_rwlock.write().ready = ready_change_payload.ready;
         }
         None => {
-            error_log(format!("player not found in ws_ready_change {}", &ready_change_payload.player_uuid));
+            error_log(format!("player not found in ws_ready_change {}", player_uuid));
         }
     }
 
-    match data.games.read().get(&ready_change_payload.game_uuid) {
-        Some(game_rwlock) => {
-            let game_read = game_rwlock.read();
-            // if all players are ready, shuffle player uuid vec, pick a random index and assign move, next player = index + 1 (wraps)
-            if game_read.players.iter().all(|uuid| {
-                let player_read = data.players.read();
-                let ready = player_read.get(uuid).unwrap().read().ready;
-                drop(player_read);
-                ready
-            }) {
-                game_read.players.clone().shuffle(&mut thread_rng());
-                let hex_pair_bag = game_read.hex_pairs_in_bag.clone();
-
-                game_rwlock.read().players.iter().for_each(|player_uuid| {
-                    match players_read.get(&player_uuid) {
-                        Some(player_rwlock) => {
-                            let mut player_write = player_rwlock.write();
-                            for i in 0..6 {
-                                match hex_pair_bag.clone().take_random_hex_pair() {
-                                    Some(hex_pair) => {
-                                        player_write.hex_pairs[i] = Some(hex_pair);
-                                    }
-                                    None => {}
-                                }
-                            }
-
-                            let _ = data.rooms_state.read().unwrap().clients.get(&ready_change_payload.player_uuid.to_string()).unwrap().send(
-                                json!({
-                                    "type": "player_game_state",
-                                    "data": {
-                                        "players": {
-                                            &ready_change_payload.player_uuid.to_string(): {
-                                                "hexPairs": player_write.hex_pairs,
-                                            }
-                                        },
-                                    }
-                                }).to_string()
-                            );
-
-                            data.rooms_state.read().unwrap().broadcast_to_room(
-                                &ready_change_payload.game_uuid.to_string(),
-                                json!({
-                                    "type": "game_state",
-                                    "data": {
-                                        "games": {
-                                            &ready_change_payload.game_uuid.to_string(): {
-                                                "status": "in_progress",
-                                                "player_move_order": game_read.players,
-                                                "board_size": game_read.board_size,
-                                                "board": [],
-                                                "name": game_read.name,
-                                                "show_progress": game_read.show_progress,
-                                                "uuid": game_read.uuid,
-                                                "players": HashMap::<Uuid, Player>::new(),
-                                            },
-                                        },
-                                    }
-                                }).to_string().as_str(),
-                                None
-                            );
-                        }
-                        None => {
-                            error_log(format!("player not found in players state: {}", &player_uuid));
-                        }
+    if start_game_if_all_ready(data, game_uuid) {
+        broadcast_public_game_state(data, game_uuid, "game_state");
+        broadcast_lobby_game_change(data, LobbyGameChange::Updated, game_uuid);
+    }
+
+    data.rooms_state.read().unwrap().broadcast_to_room(
+        &game_uuid.to_string(),
+        json!({
+            "type": "player_ready",
+            "data": {
+                "games": json!({
+                    &game_uuid.to_string(): {
+                        "players": collect_lobby_game_player_state(data, game_uuid),
                     }
-                });
+                })
+            }
+        }).to_string().as_str(),
+        None
+    );
+}
 
-                drop(game_read);
+fn check_team_choice(game: &Game, player_uuid: &Uuid, team: usize) -> Result<(), &'static str> {
+    if !game.team_mode {
+        Err("the game is not played in teams")
+    } else if game.status != GameStatus::Created {
+        Err("game has already started")
+    } else if !game.players.contains(player_uuid) {
+        Err("player is not seated in this game")
+    } else if team >= TEAM_COUNT {
+        Err("there are only two teams")
+    } else if game.team_of(player_uuid) != Some(team) && game.team_size(team) >= game.player_count as usize / TEAM_COUNT {
+        Err("the team is full")
+    } else {
+        Ok(())
+    }
+}
 
-                let mut game_write = game_rwlock.write();
-                let first_player_to_move = game_write.players.choose(&mut thread_rng()).copied();
-                game_write.player_to_move = first_player_to_move;
-                game_write.status = "in_progress".to_string();
-                drop(game_write);
+// partners are chosen before the game starts, a player moves to the other team while it has a free place
+pub async fn ws_choose_team(data: &Data<AppState>, choose_team_payload: &WsChooseTeam) {
+    let player_uuid = &choose_team_payload.player_uuid;
+    let game_uuid = &choose_team_payload.game_uuid;
 
-                data.boards.write().insert(ready_change_payload.game_uuid, Arc::new(RwLock::new(Vec::new())));
+    let choose_result = match data.games.read().get(game_uuid) {
+        Some(game_rwlock) => {
+            let mut game_write = game_rwlock.write();
+            let choose_result = check_team_choice(&game_write, player_uuid, choose_team_payload.team);
+            if choose_result.is_ok() {
+                game_write.teams.insert(*player_uuid, choose_team_payload.team);
             }
+            choose_result
+        }
+        None => Err("game not found"),
+    };
 
-            data.rooms_state.read().unwrap().broadcast_to_room(
-                &ready_change_payload.game_uuid.to_string(),
-                json!({
-                    "type": "player_ready",
-                    "data": {
-                        "games": json!({
-                            &ready_change_payload.game_uuid.to_string(): {
-                                "players": collect_lobby_game_player_state(&data, &ready_change_payload.game_uuid),
-                            }
-                        })
-                    }
-                }).to_string().as_str(),
-                None
-            );
+    if let Err(error) = choose_result {
+        data.rooms_state.read().unwrap().send_to_client(&player_uuid.to_string(), &ws_error_payload("choose_team", error));
+        return;
+    }
+
+    broadcast_to_game_room(data, game_uuid, json!({
+        "type": "player_team_changed",
+        "data": {
+            "games": json!({
+                &game_uuid.to_string(): {
+                    "players": collect_lobby_game_player_state(data, game_uuid),
+                }
+            })
         }
-        None => {
-            error_log(format!("game not found with uuid: {}", &ready_change_payload.game_uuid));
+    }));
+    broadcast_lobby_game_change(data, LobbyGameChange::Updated, game_uuid);
+}
+
+// if every seat is taken and all players are ready, shuffle the seats with the game seed, the first seat moves first, next player = index + 1 (wraps)
+// in team play both teams have to be complete first
+// the game lock is taken before the players map, like everywhere else, and nothing is broadcast while either is held
+fn start_game_if_all_ready(data: &Data<AppState>, game_uuid: &Uuid) -> bool {
+    let games = data.games.read();
+    let Some(game_rwlock) = games.get(game_uuid) else {
+        error_log(format!("game not found with uuid: {}", game_uuid));
+        return false;
+    };
+    let mut game_write = game_rwlock.write();
+    let players_read = data.players.read();
+    let all_ready = game_write.status == GameStatus::Created
+        && game_write.players.len() == game_write.player_count as usize
+        && game_write.has_full_teams()
+        && game_write.players.iter().all(|uuid| players_read.get(uuid).is_some_and(|player_rwlock| player_rwlock.read().ready));
+    if !all_ready {
+        return false;
+    }
+
+    if let Err(error) = game_write.transition_to(GameStatus::Starting) {
+        error_log(error);
+        return false;
+    }
+    game_write.shuffle_seats();
+    persist_seat_order(data, game_uuid, &game_write.players);
+
+    let rules = game_write.variant.rules();
+    for player_uuid in game_write.players.iter() {
+        match players_read.get(player_uuid) {
+            Some(player_rwlock) => {
+                let mut player_write = player_rwlock.write();
+                player_write.hex_pairs = [None; 6];
+                let draws = rules.draw_hex_pairs(&game_write.hex_pairs_in_bag, &mut player_write.hex_pairs);
+                persist_hex_pair_draws(data, game_uuid, player_uuid, 0, draws);
+                send_teammate_state(data, &game_write, &player_write);
+                send_private_player_state(data, &player_write);
+            }
+            None => {
+                error_log(format!("player not found in players state: {}", player_uuid));
+            }
         }
     }
-}
\ No newline at end of file
+    drop(players_read);
+    data.boards.write().insert(*game_uuid, Arc::new(RwLock::new(Vec::new())));
+
+    game_write.player_to_move = game_write.players.first().copied();
+    game_write.clock = game_write.time_control.map(|time_control| GameClock::new(time_control, &game_write.players, Instant::now()));
+    if let Err(error) = game_write.transition_to(GameStatus::InProgress) {
+        error_log(error);
+    }
+    persist_game_status(data, game_uuid, game_write.status);
+    let players = game_write.players.clone();
+    drop(game_write);
+    drop(games);
+
+    players.iter().for_each(|player_uuid| leave_matchmaking(data, player_uuid));
+    true
+}
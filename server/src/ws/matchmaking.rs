@@ -0,0 +1,226 @@
+use std::sync::Arc;
+use actix_web::web::Data;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+use crate::AppState;
+use crate::routes::lobby::{broadcast_lobby_game_change, create_game, CreateGameSchema, LobbyGameChange};
+use crate::util::{error_log, get_random_name};
+use crate::ws::lobby::leave_previous_game;
+use crate::ws::ws_error_payload;
+
+pub type Matchmaking = Arc<RwLock<MatchmakingQueue>>;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct MatchmakingPreferences {
+    pub player_count: i8,
+    pub board_size: i32,
+    pub rated: bool,
+}
+
+#[derive(Debug, Clone)]
+struct QueuedPlayer {
+    player_uuid: Uuid,
+    preferences: MatchmakingPreferences,
+}
+
+#[derive(Default, Debug)]
+pub struct MatchmakingQueue {
+    // ordered by enqueue time, oldest first
+    queue: Vec<QueuedPlayer>,
+}
+
+impl MatchmakingQueue {
+    pub fn enqueue(&mut self, player_uuid: Uuid, preferences: MatchmakingPreferences) {
+        self.dequeue(&player_uuid);
+        self.queue.push(QueuedPlayer { player_uuid, preferences });
+    }
+
+    pub fn dequeue(&mut self, player_uuid: &Uuid) -> bool {
+        let length_before = self.queue.len();
+        self.queue.retain(|queued_player| queued_player.player_uuid != *player_uuid);
+        self.queue.len() != length_before
+    }
+
+    // removes and returns the longest waiting group of players with the same preferences
+    pub fn take_match(&mut self) -> Option<(MatchmakingPreferences, Vec<Uuid>)> {
+        let matched: Vec<QueuedPlayer> = self.queue.iter().find_map(|oldest| {
+            let group: Vec<QueuedPlayer> = self.queue.iter()
+                .filter(|queued_player| queued_player.preferences == oldest.preferences)
+                .take(oldest.preferences.player_count as usize)
+                .cloned()
+                .collect();
+            (group.len() == oldest.preferences.player_count as usize).then_some(group)
+        })?;
+
+        self.queue.retain(|queued_player| !matched.iter().any(|matched_player| matched_player.player_uuid == queued_player.player_uuid));
+
+        Some((matched[0].preferences, matched.iter().map(|queued_player| queued_player.player_uuid).collect()))
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WsMatchmakingEnqueue {
+    pub player_uuid: Uuid,
+    #[serde(flatten)]
+    pub preferences: MatchmakingPreferences,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WsMatchmakingDequeue {
+    pub player_uuid: Uuid,
+}
+
+pub async fn ws_matchmaking_enqueue(data: &Data<AppState>, enqueue_payload: &WsMatchmakingEnqueue) {
+    let player_uuid = &enqueue_payload.player_uuid;
+    let preferences = enqueue_payload.preferences;
+
+    let error = if !data.players.read().contains_key(player_uuid) {
+        Some("player not found")
+    } else if !(2..=4).contains(&preferences.player_count) {
+        Some("player count must be between 2 and 4")
+    } else if !(6..=8).contains(&preferences.board_size) {
+        Some("board size must be between 6 and 8")
+    } else if in_running_game(data, player_uuid) {
+        Some("already playing a game")
+    } else {
+        None
+    };
+
+    if let Some(error) = error {
+        data.rooms_state.read().unwrap().send_to_client(&player_uuid.to_string(), &ws_error_payload("matchmaking_enqueue", error));
+        return;
+    }
+
+    data.matchmaking.write().enqueue(*player_uuid, preferences);
+    data.rooms_state.read().unwrap().send_to_client(&player_uuid.to_string(), json!({
+        "type": "matchmaking_queued",
+        "data": preferences,
+    }).to_string().as_str());
+
+    start_matched_games(data).await;
+}
+
+pub async fn ws_matchmaking_dequeue(data: &Data<AppState>, dequeue_payload: &WsMatchmakingDequeue) {
+    leave_matchmaking(data, &dequeue_payload.player_uuid);
+}
+
+// a player taking a seat or spectating is no longer waiting for a match
+pub fn leave_matchmaking(data: &Data<AppState>, player_uuid: &Uuid) {
+    if data.matchmaking.write().dequeue(player_uuid) {
+        data.rooms_state.read().unwrap().send_to_client(&player_uuid.to_string(), json!({
+            "type": "matchmaking_dequeued",
+            "data": {},
+        }).to_string().as_str());
+    }
+}
+
+fn in_running_game(data: &Data<AppState>, player_uuid: &Uuid) -> bool {
+    let current_game_uuid = data.players.read().get(player_uuid).and_then(|player_rwlock| player_rwlock.read().game_uuid);
+    current_game_uuid
+        .and_then(|game_uuid| data.games.read().get(&game_uuid).map(|game_rwlock| game_rwlock.read().status))
+        .is_some_and(|status| status.is_running())
+}
+
+pub async fn start_matched_games(data: &Data<AppState>) {
+    loop {
+        let Some((preferences, player_uuids)) = data.matchmaking.write().take_match() else {
+            break;
+        };
+
+        // a queued player may have been seated in a game that started meanwhile, the others wait for the next match
+        let (busy, player_uuids): (Vec<Uuid>, Vec<Uuid>) = player_uuids.into_iter().partition(|player_uuid| in_running_game(data, player_uuid));
+        if !busy.is_empty() {
+            busy.iter().for_each(|player_uuid| {
+                data.rooms_state.read().unwrap().send_to_client(&player_uuid.to_string(), json!({
+                    "type": "matchmaking_dequeued",
+                    "data": {},
+                }).to_string().as_str());
+            });
+            let mut matchmaking = data.matchmaking.write();
+            player_uuids.iter().for_each(|player_uuid| matchmaking.enqueue(*player_uuid, preferences));
+            continue;
+        }
+
+        let settings = CreateGameSchema {
+            name: format!("{} ({})", get_random_name(), if preferences.rated { "rated" } else { "unrated" }),
+            boardSize: preferences.board_size,
+            playerCount: preferences.player_count as i32,
+            showProgress: true,
+            playerUuid: player_uuids[0],
+            allowSpectators: Some(true),
+            public: Some(false),
+            rated: Some(preferences.rated),
+            timeControl: None,
+            disconnectedSeat: None,
+            teamMode: None,
+            variant: None,
+        };
+
+        let game_uuid = match create_game(data, &settings).await {
+            Ok((game_uuid, _)) => game_uuid,
+            Err(error) => {
+                error_log(format!("database error (start_matched_games) {}", error));
+                // put the players back so they are matched again with the next enqueue
+                let mut matchmaking = data.matchmaking.write();
+                player_uuids.iter().for_each(|player_uuid| matchmaking.enqueue(*player_uuid, preferences));
+                break;
+            }
+        };
+
+        seat_matched_players(data, &game_uuid, &player_uuids);
+    }
+}
+
+fn seat_matched_players(data: &Data<AppState>, game_uuid: &Uuid, player_uuids: &[Uuid]) {
+    for player_uuid in player_uuids {
+        let previous_game_uuid = data.players.read().get(player_uuid).and_then(|player_rwlock| player_rwlock.read().game_uuid);
+        if let Some(previous_game_uuid) = previous_game_uuid {
+            if let Err(error) = leave_previous_game(data, player_uuid, &previous_game_uuid) {
+                error_log(format!("could not leave game {} (seat_matched_players) {}", previous_game_uuid, error));
+            }
+        }
+
+        if let Some(player_rwlock) = data.players.read().get(player_uuid) {
+            let mut player_write = player_rwlock.write();
+            player_write.game_uuid = Some(*game_uuid);
+            player_write.ready = false;
+        }
+
+        data.rooms_state.write().unwrap().join_room(&game_uuid.to_string(), &player_uuid.to_string());
+    }
+
+    if let Some(game_rwlock) = data.games.read().get(game_uuid) {
+        game_rwlock.write().players = player_uuids.to_vec();
+    }
+
+    broadcast_lobby_game_change(data, LobbyGameChange::Updated, game_uuid);
+
+    data.rooms_state.read().unwrap().broadcast_to_room(
+        &game_uuid.to_string(),
+        json!({
+            "type": "matchmaking_matched",
+            "data": {
+                "game_uuid": game_uuid,
+            }
+        }).to_string().as_str(),
+        None
+    );
+}
+
+#[test]
+fn test_take_match_groups_players_with_same_preferences() {
+    let mut matchmaking = MatchmakingQueue::default();
+    let two_players = MatchmakingPreferences { player_count: 2, board_size: 6, rated: false };
+    let rated = MatchmakingPreferences { rated: true, ..two_players };
+    let players: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+
+    matchmaking.enqueue(players[0], two_players);
+    matchmaking.enqueue(players[1], rated);
+    assert_eq!(matchmaking.take_match(), None);
+
+    matchmaking.enqueue(players[2], two_players);
+    assert_eq!(matchmaking.take_match(), Some((two_players, vec![players[0], players[2]])));
+    assert!(matchmaking.dequeue(&players[1]));
+}
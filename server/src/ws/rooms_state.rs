@@ -1,7 +1,30 @@
 use std::collections::{HashMap, HashSet};
 use std::time::{Duration, Instant};
+use serde::Serialize;
+use serde_json::json;
 use tokio::sync::mpsc;
 
+// Room every connected client is in, used for lobby wide messages
+pub const LOBBY_ROOM: &str = "lobby";
+
+// How long the rooms of a disconnected client are remembered for it to reconnect into
+const RECONNECT_WINDOW: Duration = Duration::from_secs(120);
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct PresencePlayer {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceEvent {
+    Joined,
+    Left,
+    Disconnected,
+    Reconnected,
+}
+
 #[derive(Debug)]
 pub struct RoomsState {
     // Map of room name to set of client IDs
@@ -10,6 +33,10 @@ pub struct RoomsState {
     pub clients: HashMap<String, mpsc::UnboundedSender<String>>,
     // Last activity timestamp for each client
     client_activity: HashMap<String, Instant>,
+    // Map of client ID to the player shown in presence events
+    client_players: HashMap<String, PresencePlayer>,
+    // Map of client ID to the rooms it was in when it disconnected
+    disconnected_clients: HashMap<String, (Instant, Vec<String>)>,
 }
 
 impl RoomsState {
@@ -18,6 +45,8 @@ impl RoomsState {
             rooms: HashMap::new(),
             clients: HashMap::new(),
             client_activity: HashMap::new(),
+            client_players: HashMap::new(),
+            disconnected_clients: HashMap::new(),
         }
     }
 
@@ -28,16 +57,20 @@ impl RoomsState {
             .insert(client_id.to_string());
 
         if client_added {
-            let join_msg = format!("SYSTEM:User {} joined room {}", client_id, room_name);
-            self.broadcast_to_room(room_name, &join_msg, Some(client_id));
+            self.broadcast_presence(room_name, client_id, PresenceEvent::Joined);
         }
 
+        self.send_presence_snapshot(room_name, client_id);
         self.client_activity.insert(client_id.to_string(), Instant::now());
 
         client_added
     }
 
     pub fn leave_room(&mut self, room_name: &str, client_id: &str) -> bool {
+        self.remove_from_room(room_name, client_id, PresenceEvent::Left)
+    }
+
+    fn remove_from_room(&mut self, room_name: &str, client_id: &str, event: PresenceEvent) -> bool {
         let mut client_removed = false;
 
         if let Some(room) = self.rooms.get_mut(room_name) {
@@ -50,33 +83,78 @@ impl RoomsState {
 
         // Notify room about user leaving if client was removed
         if client_removed {
-            let leave_msg = format!("SYSTEM:User {} left room {}", client_id, room_name);
-            self.broadcast_to_room(room_name, &leave_msg, None);
+            self.broadcast_presence(room_name, client_id, event);
         }
 
         client_removed
     }
 
-    fn is_in_room(&self, room_name: &str, client_id: &str) -> bool {
+    fn broadcast_presence(&self, room_name: &str, client_id: &str, event: PresenceEvent) {
+        let presence_msg = json!({
+            "type": "presence",
+            "data": {
+                "room": room_name,
+                "event": event,
+                "player": self.client_players.get(client_id).cloned().unwrap_or_default(),
+            }
+        }).to_string();
+
+        self.broadcast_to_room(room_name, &presence_msg, Some(client_id));
+    }
+
+    fn send_presence_snapshot(&self, room_name: &str, client_id: &str) {
+        let snapshot_msg = json!({
+            "type": "presence_snapshot",
+            "data": {
+                "room": room_name,
+                "players": self.presence_snapshot(room_name),
+            }
+        }).to_string();
+
+        self.send_to_client(client_id, &snapshot_msg);
+    }
+
+    pub fn is_in_room(&self, room_name: &str, client_id: &str) -> bool {
         self.rooms.get(room_name)
             .map_or(false, |clients| clients.contains(client_id))
     }
 
     pub fn broadcast_to_room(&self, room_name: &str, message: &str, skip_client_id: Option<&str>) {
+        self.broadcast_to_room_with(room_name, skip_client_id, |_| Some(message.to_string()));
+    }
+
+    // Builds the message for every client in the room, clients the closure returns None for get nothing
+    pub fn broadcast_to_room_with(&self, room_name: &str, skip_client_id: Option<&str>, message_for: impl Fn(&str) -> Option<String>) {
         if let Some(room) = self.rooms.get(room_name) {
             for client_id in room {
                 if skip_client_id.map_or(true, |id| id != client_id) {
-                    if let Some(client_tx) = self.clients.get(client_id) {
-                        let _ = client_tx.send(message.to_string());
+                    if let (Some(client_tx), Some(message)) = (self.clients.get(client_id), message_for(client_id)) {
+                        let _ = client_tx.send(message);
                     }
                 }
             }
         }
     }
 
-    pub fn register_client(&mut self, client_id: &str, tx: mpsc::UnboundedSender<String>) {
+    // Returns the rooms the client was put back into when reconnecting within RECONNECT_WINDOW
+    pub fn register_client(&mut self, client_id: &str, tx: mpsc::UnboundedSender<String>, player: PresencePlayer) -> Vec<String> {
         self.clients.insert(client_id.to_string(), tx);
+        self.client_players.insert(client_id.to_string(), player);
         self.client_activity.insert(client_id.to_string(), Instant::now());
+
+        let rooms_to_rejoin = match self.disconnected_clients.remove(client_id) {
+            Some((disconnected_at, rooms)) if disconnected_at.elapsed() <= RECONNECT_WINDOW => rooms,
+            _ => Vec::new(),
+        };
+
+        for room_name in &rooms_to_rejoin {
+            if self.rooms.entry(room_name.clone()).or_default().insert(client_id.to_string()) {
+                self.broadcast_presence(room_name, client_id, PresenceEvent::Reconnected);
+            }
+            self.send_presence_snapshot(room_name, client_id);
+        }
+
+        rooms_to_rejoin
     }
 
     pub fn update_client_activity(&mut self, client_id: &str) {
@@ -101,6 +179,8 @@ impl RoomsState {
             println!("Removed stale client: {}", client_id);
         }
 
+        self.disconnected_clients.retain(|_, (disconnected_at, _)| now.duration_since(*disconnected_at) <= RECONNECT_WINDOW);
+
         stale_clients
     }
 
@@ -117,19 +197,70 @@ impl RoomsState {
             })
             .collect();
 
-        for room_name in rooms_to_leave {
-            self.leave_room(&room_name, client_id);
+        for room_name in &rooms_to_leave {
+            self.remove_from_room(room_name, client_id, PresenceEvent::Disconnected);
+        }
+
+        if !rooms_to_leave.is_empty() {
+            self.disconnected_clients.insert(client_id.to_string(), (Instant::now(), rooms_to_leave));
         }
 
         self.clients.remove(client_id);
         self.client_activity.remove(client_id);
+        self.client_players.remove(client_id);
+    }
+
+    fn presence_snapshot(&self, room_name: &str) -> Vec<PresencePlayer> {
+        self.rooms.get(room_name)
+            .map(|clients| clients.iter().filter_map(|id| self.client_players.get(id).cloned()).collect())
+            .unwrap_or_default()
     }
 
-    fn send_to_client(&self, client_id: &str, message: &str) -> bool {
+    pub fn send_to_client(&self, client_id: &str, message: &str) -> bool {
         if let Some(tx) = self.clients.get(client_id) {
             tx.send(message.to_string()).is_ok()
         } else {
             false
         }
     }
-}
\ No newline at end of file
+}
+#[test]
+fn test_presence_events_on_disconnect_and_reconnect() {
+    let mut rooms_state = RoomsState::new();
+    let (tx_1, mut rx_1) = mpsc::unbounded_channel::<String>();
+    let (tx_2, _rx_2) = mpsc::unbounded_channel::<String>();
+
+    rooms_state.register_client("1", tx_1, PresencePlayer { id: 1, name: "aija".to_string() });
+    rooms_state.register_client("2", tx_2.clone(), PresencePlayer { id: 2, name: "desa".to_string() });
+    rooms_state.join_room("game", "1");
+    rooms_state.join_room("game", "2");
+    rooms_state.remove_client("2");
+
+    let rejoined_rooms = rooms_state.register_client("2", tx_2, PresencePlayer { id: 2, name: "desa".to_string() });
+    assert_eq!(rejoined_rooms, vec!["game".to_string()]);
+
+    let events: Vec<serde_json::Value> = std::iter::from_fn(|| rx_1.try_recv().ok())
+        .map(|message| serde_json::from_str(&message).unwrap())
+        .filter(|message: &serde_json::Value| message["type"] == "presence")
+        .map(|message| message["data"]["event"].clone())
+        .collect();
+    assert_eq!(events, vec!["joined", "disconnected", "reconnected"]);
+}
+
+#[test]
+fn test_broadcast_to_room_with_builds_message_per_client() {
+    let mut rooms_state = RoomsState::new();
+    let (tx_1, mut rx_1) = mpsc::unbounded_channel::<String>();
+    let (tx_2, mut rx_2) = mpsc::unbounded_channel::<String>();
+
+    rooms_state.register_client("1", tx_1, PresencePlayer { id: 1, name: "aija".to_string() });
+    rooms_state.register_client("2", tx_2, PresencePlayer { id: 2, name: "desa".to_string() });
+    rooms_state.join_room("game", "1");
+    rooms_state.join_room("game", "2");
+    while rx_1.try_recv().is_ok() {}
+    while rx_2.try_recv().is_ok() {}
+
+    rooms_state.broadcast_to_room_with("game", None, |client_id| (client_id == "1").then(|| format!("for {}", client_id)));
+    assert_eq!(rx_1.try_recv().ok(), Some("for 1".to_string()));
+    assert!(rx_2.try_recv().is_err());
+}
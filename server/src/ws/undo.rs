@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+use actix_web::web::Data;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+use crate::AppState;
+use crate::types::GameStatus;
+use crate::ws::game::send_teammate_state;
+use crate::ws::game_writes::GameWrite;
+use crate::ws::view::{broadcast_public_game_state, send_private_player_state};
+use crate::ws::{broadcast_to_game_room, ws_error_payload};
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WsUndoRequest {
+    pub player_uuid: Uuid,
+    pub game_uuid: Uuid,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WsUndoResponse {
+    pub player_uuid: Uuid,
+    pub game_uuid: Uuid,
+    pub accept: bool,
+}
+
+fn send_error(data: &Data<AppState>, player_uuid: &Uuid, source: &str, message: &str) {
+    data.rooms_state.read().unwrap().send_to_client(&player_uuid.to_string(), &ws_error_payload(source, message));
+}
+
+fn player_id(data: &Data<AppState>, player_uuid: &Uuid) -> Option<i32> {
+    data.players.read().get(player_uuid).map(|player_rwlock| player_rwlock.read().id)
+}
+
+// the other side has to agree, partners of the player and bots do not get asked, neither do seats that can not answer
+fn undo_opponents(data: &Data<AppState>, game_uuid: &Uuid, player_uuid: &Uuid) -> Vec<Uuid> {
+    data.games.read().get(game_uuid).map(|game_rwlock| {
+        let game_read = game_rwlock.read();
+        let teammates = game_read.teammates(player_uuid);
+        let players_read = data.players.read();
+        game_read.players.iter()
+            .filter(|uuid| *uuid != player_uuid && !teammates.contains(uuid))
+            .filter(|uuid| !game_read.forfeited.contains(uuid) && !game_read.disconnected_seats.contains_key(uuid))
+            .filter(|uuid| players_read.get(uuid).is_some_and(|player_rwlock| player_rwlock.read().bot.is_none()))
+            .copied()
+            .collect()
+    }).unwrap_or_default()
+}
+
+// reverts the player's last placement and sends everyone the game as it was before it
+fn undo_last_placement(data: &Data<AppState>, game_uuid: &Uuid, player_uuid: &Uuid) -> Result<(), &'static str> {
+    let games = data.games.read();
+    let game_rwlock = games.get(game_uuid).ok_or("game not found")?;
+    let mut game_write = game_rwlock.write();
+    if game_write.status != GameStatus::InProgress {
+        return Err("game is not in progress");
+    }
+    // a placement made in the meantime replaces the snapshot and cancels the request
+    game_write.undo_accepted_by.take().ok_or("the undo request is no longer pending")?;
+    let snapshot = game_write.last_placement.take().filter(|snapshot| snapshot.player_uuid == *player_uuid).ok_or("there is no placement to undo")?;
+    let move_number = snapshot.move_number;
+
+    let players_read = data.players.read();
+    let mut player_write = players_read.get(player_uuid).ok_or("player not found")?.write();
+    let boards = data.boards.read();
+    let mut board_write = boards.get(game_uuid).ok_or("board not found")?.write();
+    snapshot.restore(&mut game_write, &mut player_write, &mut board_write)?;
+    drop(board_write);
+    persist_undone_placement(data, game_uuid, player_uuid, move_number);
+
+    send_private_player_state(data, &player_write);
+    send_teammate_state(data, &game_write, &player_write);
+    let player_id = player_write.id;
+    drop(player_write);
+    drop(players_read);
+    drop(game_write);
+    drop(games);
+
+    broadcast_public_game_state(data, game_uuid, "game_state");
+    broadcast_to_game_room(data, game_uuid, json!({
+        "type": "undo_applied",
+        "data": {
+            "game_uuid": game_uuid,
+            "player_id": player_id,
+            "move_number": move_number,
+        }
+    }));
+    Ok(())
+}
+
+fn persist_undone_placement(data: &Data<AppState>, game_uuid: &Uuid, player_uuid: &Uuid, move_number: u32) {
+    data.game_writes.lock().push(&data.postgres_pool, game_uuid, GameWrite::UndonePlacement { player_uuid: *player_uuid, move_number });
+}
+
+fn apply_undo(data: &Data<AppState>, game_uuid: &Uuid, player_uuid: &Uuid) {
+    if let Err(error) = undo_last_placement(data, game_uuid, player_uuid) {
+        send_error(data, player_uuid, "undo_request", error);
+    }
+}
+
+pub async fn ws_undo_request(data: &Data<AppState>, undo_request_payload: &WsUndoRequest) {
+    let player_uuid = &undo_request_payload.player_uuid;
+    let game_uuid = &undo_request_payload.game_uuid;
+
+    let request_result = match data.games.read().get(game_uuid) {
+        Some(game_rwlock) => {
+            let mut game_write = game_rwlock.write();
+            if game_write.status != GameStatus::InProgress {
+                Err("game is not in progress")
+            } else if game_write.last_placement.as_ref().is_none_or(|snapshot| snapshot.player_uuid != *player_uuid) {
+                Err("only the last placement can be taken back, before the next player acts")
+            } else if game_write.undo_accepted_by.is_some() {
+                Err("an undo request is already pending")
+            } else {
+                game_write.undo_accepted_by = Some(HashSet::new());
+                Ok(game_write.last_placement.as_ref().map_or(0, |snapshot| snapshot.move_number))
+            }
+        }
+        None => Err("game not found"),
+    };
+    let move_number = match request_result {
+        Ok(move_number) => move_number,
+        Err(error) => {
+            send_error(data, player_uuid, "undo_request", error);
+            return;
+        }
+    };
+
+    let opponents = undo_opponents(data, game_uuid, player_uuid);
+    if opponents.is_empty() {
+        apply_undo(data, game_uuid, player_uuid);
+        return;
+    }
+
+    broadcast_to_game_room(data, game_uuid, json!({
+        "type": "undo_requested",
+        "data": {
+            "game_uuid": game_uuid,
+            "player_id": player_id(data, player_uuid),
+            "move_number": move_number,
+            "accepts_needed": opponents.len(),
+        }
+    }));
+}
+
+pub async fn ws_undo_response(data: &Data<AppState>, undo_response_payload: &WsUndoResponse) {
+    let player_uuid = &undo_response_payload.player_uuid;
+    let game_uuid = &undo_response_payload.game_uuid;
+
+    let Some(requester_uuid) = data.games.read().get(game_uuid).and_then(|game_rwlock| {
+        let game_read = game_rwlock.read();
+        game_read.undo_accepted_by.as_ref().and(game_read.last_placement.as_ref()).map(|snapshot| snapshot.player_uuid)
+    }) else {
+        send_error(data, player_uuid, "undo_response", "there is no pending undo request");
+        return;
+    };
+
+    let opponents = undo_opponents(data, game_uuid, &requester_uuid);
+    if !opponents.contains(player_uuid) {
+        send_error(data, player_uuid, "undo_response", "only opponents of the player can answer the undo request");
+        return;
+    }
+
+    if !undo_response_payload.accept {
+        if let Some(game_rwlock) = data.games.read().get(game_uuid) {
+            game_rwlock.write().undo_accepted_by = None;
+        }
+        broadcast_to_game_room(data, game_uuid, json!({
+            "type": "undo_declined",
+            "data": {
+                "game_uuid": game_uuid,
+                "player_id": player_id(data, player_uuid),
+            }
+        }));
+        return;
+    }
+
+    let accepts = data.games.read().get(game_uuid).and_then(|game_rwlock| {
+        let mut game_write = game_rwlock.write();
+        let accepted_by = game_write.undo_accepted_by.as_mut()?;
+        accepted_by.insert(*player_uuid);
+        Some(accepted_by.iter().filter(|uuid| opponents.contains(uuid)).count())
+    });
+
+    match accepts {
+        None => send_error(data, player_uuid, "undo_response", "there is no pending undo request"),
+        Some(accepts) if accepts < opponents.len() => broadcast_to_game_room(data, game_uuid, json!({
+            "type": "undo_accepted",
+            "data": {
+                "game_uuid": game_uuid,
+                "player_id": player_id(data, player_uuid),
+                "accepts": accepts,
+                "accepts_needed": opponents.len(),
+            }
+        })),
+        Some(_) => apply_undo(data, game_uuid, &requester_uuid),
+    }
+}
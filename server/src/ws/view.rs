@@ -0,0 +1,111 @@
+// What a client gets to see of a game. The public view is built per recipient, it never has a rack in it
+// and leaves out the progress the viewer may not see yet. The private view is a player's own rack and is
+// only ever sent to that player.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use actix_web::web::Data;
+use parking_lot::RwLock;
+use serde_json::json;
+use uuid::Uuid;
+use crate::AppState;
+use crate::types::{BoardHex, Game, Player};
+use crate::util::error_log;
+use crate::ws::game::collect_clock_state;
+
+pub fn public_game_view(game: &Game, players: &HashMap<Uuid, Arc<RwLock<Player>>>, board: &[BoardHex], viewer_uuid: &Uuid) -> serde_json::Value {
+    let player_views = game.players.iter().filter_map(|uuid| players.get(uuid)).map(|player_rwlock| {
+        let player_read = player_rwlock.read();
+        (player_read.id.to_string(), json!({
+            "id": player_read.id,
+            "name": player_read.name,
+            "progress": game.can_see_progress(viewer_uuid, &player_read.uuid).then_some(&player_read.progress),
+            "team": game.team_of(&player_read.uuid),
+        }))
+    }).collect::<serde_json::Map<String, serde_json::Value>>();
+
+    let player_id = |uuid: &Uuid| players.get(uuid).map(|player_rwlock| player_rwlock.read().id);
+
+    json!({
+        "status": game.status,
+        "player_move_order": game.players.iter().filter_map(player_id).collect::<Vec<i32>>(),
+        "player_to_move": game.player_to_move.as_ref().and_then(player_id),
+        "board_size": game.board_size,
+        "board": board,
+        "name": game.name,
+        "show_progress": game.show_progress,
+        "team_mode": game.team_mode,
+        "variant": game.variant,
+        "uuid": game.uuid,
+        "players": player_views,
+        "spectator_count": game.spectators.len(),
+        "clock": collect_clock_state(game, players),
+    })
+}
+
+pub fn private_player_view(player: &Player) -> serde_json::Value {
+    json!({
+        "players": {
+            player.uuid.to_string(): {
+                "hexPairs": player.hex_pairs,
+                "movesInTurn": player.moves_in_turn,
+            }
+        },
+    })
+}
+
+// bots have no connection, sending to them does nothing
+pub fn send_private_player_state(data: &Data<AppState>, player: &Player) {
+    let payload = json!({
+        "type": "player_game_state",
+        "data": private_player_view(player),
+    }).to_string();
+    data.rooms_state.read().unwrap().send_to_client(&player.uuid.to_string(), &payload);
+}
+
+// sends every client in the game room the public view of the game as that client may see it
+pub fn broadcast_public_game_state(data: &Data<AppState>, game_uuid: &Uuid, message_type: &str) {
+    let games = data.games.read();
+    let Some(game_rwlock) = games.get(game_uuid) else {
+        error_log(format!("game not found while broadcasting public game state: {}", game_uuid));
+        return;
+    };
+    let game_read = game_rwlock.read();
+    let players_read = data.players.read();
+    let board = data.boards.read().get(game_uuid).map(|board| board.read().clone()).unwrap_or_default();
+
+    data.rooms_state.read().unwrap().broadcast_to_room_with(&game_uuid.to_string(), None, |client_id| {
+        let viewer_uuid = Uuid::parse_str(client_id).ok()?;
+        Some(json!({
+            "type": message_type,
+            "data": {
+                "games": {
+                    game_uuid.to_string(): public_game_view(&game_read, &players_read, &board, &viewer_uuid),
+                },
+            }
+        }).to_string())
+    });
+}
+
+#[test]
+fn test_private_player_view_holds_only_that_players_rack() {
+    let player = Player {
+        name: "aija".to_string(),
+        ready: true,
+        uuid: Uuid::new_v4(),
+        id: 1,
+        game_uuid: None,
+        hex_pairs: [Some([0, 1]), None, None, None, None, Some([2, 2])],
+        moves_in_turn: 1,
+        progress: crate::types::Progress::new(),
+        moderator: false,
+        rating: 1500.0,
+        bot: None,
+    };
+
+    let view = private_player_view(&player);
+    let players = view["players"].as_object().unwrap();
+    assert_eq!(players.len(), 1);
+    assert_eq!(players[&player.uuid.to_string()]["hexPairs"], json!(player.hex_pairs));
+    assert!(players[&player.uuid.to_string()].get("progress").is_none());
+}